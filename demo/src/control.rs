@@ -1,11 +1,52 @@
-use winit::event::{WindowEvent, KeyboardInput, VirtualKeyCode, ElementState};
+use winit::event::{WindowEvent, KeyboardInput, VirtualKeyCode, ElementState, MouseScrollDelta};
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    FirstPerson,
+    // Behind the player, or in front of it looking back, pulled in when blocks are in the way.
+    ThirdPersonBack,
+    ThirdPersonFront,
+    // Around a point, for inspecting builds. Starts at the block the player looks at.
+    Orbit
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 4] = [
+        CameraMode::FirstPerson,
+        CameraMode::ThirdPersonBack,
+        CameraMode::ThirdPersonFront,
+        CameraMode::Orbit
+    ];
+
+    fn next(self) -> Self {
+        let i = Self::ALL.iter().position(|&mode| mode == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    pub fn shows_player(self) -> bool {
+        matches!(self, CameraMode::ThirdPersonBack | CameraMode::ThirdPersonFront)
+    }
+}
+
+// The player flies freely and is looked through, or followed, depending on the mode. In orbit
+// mode the same keys and the mouse move the orbit instead, and the wheel zooms.
 pub struct CameraControl {
+    mode: CameraMode,
+    next_mode: Option<CameraMode>,
     cmds: u32,
     updated_at: instant::Instant,
     rot_right: f32,
     rot_up: f32,
-    mouse_rot: bool
+    zoom: f32,
+    mouse_rot: bool,
+    // The eye of the player.
+    pub pos: cgmath::Point3<f32>,
+    pub yaw: cgmath::Deg<f32>,
+    pub pitch: cgmath::Deg<f32>,
+    orbit_target: cgmath::Point3<f32>,
+    orbit_distance: f32,
+    orbit_yaw: cgmath::Deg<f32>,
+    orbit_pitch: cgmath::Deg<f32>
 }
 
 impl CameraControl {
@@ -21,17 +62,42 @@ impl CameraControl {
     const CMD_ROT_DOWN : u32 = 1 << 9;
     const MOV_SPEED: f32 = 5.0;
     const ROT_SPEED: cgmath::Deg<f32> = cgmath::Deg(0.2);
+    const THIRD_PERSON_DISTANCE: f32 = 4.0;
+    // Kept between the camera and the block it is pulled in by, so the near plane doesn't clip it.
+    const COLLISION_MARGIN: f32 = 0.2;
+    const ORBIT_DISTANCE: std::ops::RangeInclusive<f32> = 2.0..=64.0;
+    // How far the orbit looks for a block to start around.
+    const ORBIT_REACH: f32 = 32.0;
 
-    pub fn new() -> Self {
+    pub fn new(camera: &renderer::camera::Camera) -> Self {
         Self {
+            mode: CameraMode::FirstPerson,
+            next_mode: None,
             cmds: 0,
             updated_at: instant::Instant::now(),
             rot_right: 0.0,
             rot_up: 0.0,
-            mouse_rot: true
+            zoom: 0.0,
+            mouse_rot: true,
+            pos: camera.pos,
+            yaw: camera.yaw,
+            pitch: camera.pitch,
+            orbit_target: camera.pos,
+            orbit_distance: *Self::ORBIT_DISTANCE.start(),
+            orbit_yaw: camera.yaw,
+            orbit_pitch: camera.pitch
         }
     }
 
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    // Takes effect on the next `update_camera`.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.next_mode = Some(mode);
+    }
+
     pub fn handle_events(&mut self, event: &WindowEvent) -> bool {
         match event {
             WindowEvent::KeyboardInput {
@@ -58,6 +124,10 @@ impl CameraControl {
                         self.mouse_rot = !self.mouse_rot;
                         true
                     }
+                    VirtualKeyCode::F5 if *state == ElementState::Pressed => {
+                        self.set_mode(self.next_mode.unwrap_or(self.mode).next());
+                        true
+                    }
                     _ => false
                 };
                 if *state == ElementState::Pressed {
@@ -67,6 +137,14 @@ impl CameraControl {
                 }
                 ok
             },
+            // Not consumed, so that the GUI can scroll too.
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.zoom += match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 50.0
+                };
+                false
+            }
             _ => false
         }
     }
@@ -78,36 +156,86 @@ impl CameraControl {
         }
     }
 
-    pub fn update_camera(&mut self, camera: &mut renderer::camera::Camera) {
-        use cgmath::Angle;
+    pub fn update_camera(&mut self, camera: &mut renderer::camera::Camera, world: &impl renderer::terrain::WorldInterface) {
+        use cgmath::{Angle, MetricSpace};
 
         let now = instant::Instant::now();
         let time_span = (now - self.updated_at).as_secs_f32();
         self.updated_at = now;
 
+        if let Some(mode) = self.next_mode.take() {
+            if mode == CameraMode::Orbit && self.mode != CameraMode::Orbit {
+                let player = renderer::camera::Camera::new(self.pos, self.yaw, self.pitch, camera.fovy, camera.aspect);
+                let ray = renderer::camera::Ray { origin: self.pos, dir: player.forward() };
+                self.orbit_target = match renderer::terrain::raycast(world, &ray, Self::ORBIT_REACH) {
+                    Some(hit) => cgmath::Point3::from(hit.coord.0.map(|v| v as f32 + 0.5)),
+                    None => self.pos + ray.dir * 8.0
+                };
+                self.orbit_distance = self.pos.distance(self.orbit_target).clamp(*Self::ORBIT_DISTANCE.start(), *Self::ORBIT_DISTANCE.end());
+                self.orbit_yaw = self.yaw;
+                self.orbit_pitch = self.pitch;
+            }
+            self.mode = mode;
+        }
+
+        let orbit = self.mode == CameraMode::Orbit;
+        let (pos, yaw, pitch) = if orbit {
+            (&mut self.orbit_target, &mut self.orbit_yaw, &mut self.orbit_pitch)
+        } else {
+            (&mut self.pos, &mut self.yaw, &mut self.pitch)
+        };
+
         let mov = Self::MOV_SPEED * time_span;
         let rot = Self::ROT_SPEED * 500.0 * time_span;
+        *yaw += Self::ROT_SPEED * self.rot_right;
+        *pitch += Self::ROT_SPEED * self.rot_up;
+        if self.cmds & Self::CMD_ROT_RIGHT != 0 { *yaw += rot; }
+        if self.cmds & Self::CMD_ROT_LEFT  != 0 { *yaw -= rot; }
+        if self.cmds & Self::CMD_ROT_UP    != 0 { *pitch += rot; }
+        if self.cmds & Self::CMD_ROT_DOWN  != 0 { *pitch -= rot; }
+        if *pitch > cgmath::Deg(89.0) { *pitch = cgmath::Deg(89.0); }
+        if *pitch < cgmath::Deg(-89.0) { *pitch = cgmath::Deg(-89.0); }
 
-        camera.yaw += Self::ROT_SPEED * self.rot_right;
-        camera.pitch += Self::ROT_SPEED * self.rot_up;
-        if self.cmds & Self::CMD_ROT_RIGHT != 0 { camera.yaw += rot; }
-        if self.cmds & Self::CMD_ROT_LEFT  != 0 { camera.yaw -= rot; }
-        if self.cmds & Self::CMD_ROT_UP    != 0 { camera.pitch += rot; }
-        if self.cmds & Self::CMD_ROT_DOWN  != 0 { camera.pitch -= rot; }
-        if camera.pitch > cgmath::Deg(89.0) { camera.pitch = cgmath::Deg(89.0); }
-        if camera.pitch < cgmath::Deg(-89.0) { camera.pitch = cgmath::Deg(-89.0); }
-
-        let front = cgmath::Vector3::new(camera.yaw.cos(), 0.0, camera.yaw.sin());
+        let front = cgmath::Vector3::new(yaw.cos(), 0.0, yaw.sin());
         let up = cgmath::Vector3::unit_y();
 
-        if self.cmds & Self::CMD_FRONT != 0 { camera.pos += mov * front; }
-        if self.cmds & Self::CMD_BACK  != 0 { camera.pos -= mov * front; }
-        if self.cmds & Self::CMD_RIGHT != 0 { camera.pos += mov * front.cross(up); }
-        if self.cmds & Self::CMD_LEFT  != 0 { camera.pos -= mov * front.cross(up); }
-        if self.cmds & Self::CMD_UP    != 0 { camera.pos += mov * up; }
-        if self.cmds & Self::CMD_DOWN  != 0 { camera.pos -= mov * up; }
+        if self.cmds & Self::CMD_FRONT != 0 { *pos += mov * front; }
+        if self.cmds & Self::CMD_BACK  != 0 { *pos -= mov * front; }
+        if self.cmds & Self::CMD_RIGHT != 0 { *pos += mov * front.cross(up); }
+        if self.cmds & Self::CMD_LEFT  != 0 { *pos -= mov * front.cross(up); }
+        if self.cmds & Self::CMD_UP    != 0 { *pos += mov * up; }
+        if self.cmds & Self::CMD_DOWN  != 0 { *pos -= mov * up; }
+
+        if orbit {
+            self.orbit_distance = (self.orbit_distance * 0.9f32.powf(self.zoom))
+                .clamp(*Self::ORBIT_DISTANCE.start(), *Self::ORBIT_DISTANCE.end());
+        }
 
         self.rot_right = 0.0;
         self.rot_up = 0.0;
+        self.zoom = 0.0;
+
+        (camera.yaw, camera.pitch) = if orbit { (self.orbit_yaw, self.orbit_pitch) } else { (self.yaw, self.pitch) };
+        let forward = camera.forward();
+        camera.pos = match self.mode {
+            CameraMode::FirstPerson => self.pos,
+            CameraMode::ThirdPersonBack => self.pulled_in(world, -forward),
+            CameraMode::ThirdPersonFront => {
+                (camera.yaw, camera.pitch) = (self.yaw + cgmath::Deg(180.0), -self.pitch);
+                self.pulled_in(world, forward)
+            }
+            CameraMode::Orbit => self.orbit_target - forward * self.orbit_distance
+        };
+    }
+
+    // The camera position `THIRD_PERSON_DISTANCE` away from the player along `dir`, or closer
+    // if a block is in the way.
+    fn pulled_in(&self, world: &impl renderer::terrain::WorldInterface, dir: cgmath::Vector3<f32>) -> cgmath::Point3<f32> {
+        let ray = renderer::camera::Ray { origin: self.pos, dir };
+        let distance = match renderer::terrain::raycast(world, &ray, Self::THIRD_PERSON_DISTANCE) {
+            Some(hit) => (hit.distance - Self::COLLISION_MARGIN).max(0.0),
+            None => Self::THIRD_PERSON_DISTANCE
+        };
+        self.pos + dir * distance
     }
 }
@@ -1,7 +1,8 @@
 pub struct GuiRenderer {
     context: egui::Context,
     state: egui_winit::State,
-    renderer: egui_wgpu::Renderer
+    renderer: egui_wgpu::Renderer,
+    output: Option<egui::FullOutput>
 }
 
 impl GuiRenderer {
@@ -25,7 +26,8 @@ impl GuiRenderer {
         Self {
             context,
             state: window_state,
-            renderer
+            renderer,
+            output: None
         }
     }
 
@@ -33,46 +35,228 @@ impl GuiRenderer {
         self.state.on_window_event(&self.context, event).consumed
     }
 
-    pub fn render(&mut self, window: &winit::window::Window, device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+    pub fn run(&mut self, window: &winit::window::Window, run_ui: impl FnOnce(&egui::Context)) {
+        let raw_input = self.state.take_egui_input(window);
+        let mut full_output = self.context.run(raw_input, run_ui);
+
+        let platform_output = std::mem::take(&mut full_output.platform_output);
+        self.state.handle_platform_output(window, &self.context, platform_output);
+
+        self.output = Some(full_output);
+    }
+
+    // Draws the same frame to every view, e.g. the surface and a screenshot.
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, encoder: &mut wgpu::CommandEncoder, views: &[&wgpu::TextureView]) {
+        let Some(full_output) = self.output.take() else { return; };
+
         let screen_descriptor = egui_wgpu::renderer::ScreenDescriptor {
             size_in_pixels: [config.width, config.height],
             pixels_per_point: self.context.pixels_per_point()
         };
 
-        let raw_input = self.state.take_egui_input(window);
-        let full_output = self.context.run(raw_input, |ctx| {
-            egui::SidePanel::left("My Panel").show(ctx, |ui| {
-                ui.label("Hello egui!");
-            });
-        });
-
-        self.state.handle_platform_output(window, &self.context, full_output.platform_output);
-
         let tris = self.context.tessellate(full_output.shapes, full_output.pixels_per_point);
         for (id, image_delta) in &full_output.textures_delta.set {
             self.renderer.update_texture(device, queue, *id, image_delta);
         }
         self.renderer.update_buffers(device, queue, encoder, &tris, &screen_descriptor);
 
-        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("[egui] Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view,
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Load,
-                    store: wgpu::StoreOp::Store
-                }
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None
-        });
-        self.renderer.render(&mut rpass, &tris, &screen_descriptor);
-        drop(rpass);
+        for view in views {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("[egui] Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store
+                    }
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None
+            });
+            self.renderer.render(&mut rpass, &tris, &screen_descriptor);
+        }
 
         for x in &full_output.textures_delta.free {
             self.renderer.free_texture(x);
         }
     }
 }
+
+pub fn settings_ui(
+    ui: &mut egui::Ui,
+    settings: &mut crate::settings::RenderSettings,
+    supported_sample_counts: &[u32],
+    supported_present_modes: &[wgpu::PresentMode]
+) {
+    ui.heading("Rendering");
+    ui.horizontal_wrapped(|ui| {
+        for &mode in supported_present_modes {
+            ui.radio_value(&mut settings.present_mode, mode, format!("{mode:?}"));
+        }
+    });
+    ui.add(egui::Slider::new(&mut settings.fovy.0, 30.0..=110.0).text("Field of view"));
+    ui.add(egui::Slider::new(&mut settings.render_distance, 16.0..=256.0).logarithmic(true).text("Render distance"));
+    ui.add(egui::Slider::new(&mut settings.render_scale, 0.25..=2.0).text("Render scale"));
+
+    msaa_ui(ui, &mut settings.sample_count, supported_sample_counts);
+    sampler_ui(ui, &mut settings.filtering);
+}
+
+fn msaa_ui(ui: &mut egui::Ui, sample_count: &mut u32, supported: &[u32]) {
+    ui.heading("Anti-aliasing");
+    ui.horizontal(|ui| {
+        for &count in supported {
+            let label = if count == 1 { "Off".to_string() } else { format!("{count}x") };
+            ui.radio_value(sample_count, count, label);
+        }
+    });
+}
+
+pub fn camera_ui(ui: &mut egui::Ui, control: &mut crate::control::CameraControl) {
+    use crate::control::CameraMode;
+
+    ui.heading("Camera (F5)");
+    let mut mode = control.mode();
+    for (value, label) in CameraMode::ALL.into_iter().zip(["First person", "Third person", "Third person front", "Orbit"]) {
+        ui.radio_value(&mut mode, value, label);
+    }
+    if mode != control.mode() {
+        control.set_mode(mode);
+    }
+}
+
+pub fn projection_ui(ui: &mut egui::Ui, projection: &mut renderer::camera::Projection) {
+    use renderer::camera::ProjectionMode;
+
+    ui.heading("Projection");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut projection.mode, ProjectionMode::Perspective, "Perspective");
+        ui.radio_value(&mut projection.mode, ProjectionMode::Orthographic, "Orthographic");
+    });
+    ui.add(egui::Slider::new(&mut projection.near, 0.01..=1.0).logarithmic(true).text("Near"));
+    ui.add(egui::Slider::new(&mut projection.zoom, 0.25..=4.0).logarithmic(true).text("Orthographic zoom"));
+    ui.checkbox(&mut projection.reversed_z, "Reversed depth");
+}
+
+pub fn terrain_ui(ui: &mut egui::Ui, gpu_meshing: &mut bool) {
+    ui.heading("Terrain");
+    ui.checkbox(gpu_meshing, "Mesh bulk updates on the GPU");
+}
+
+fn sampler_ui(ui: &mut egui::Ui, sampler: &mut renderer::terrain::SamplerSettings) {
+    ui.heading("Texture filtering");
+    ui.add(egui::Slider::new(&mut sampler.anisotropy, 1..=16).logarithmic(true).text("Anisotropy"));
+    ui.checkbox(&mut sampler.mipmaps, "Mipmaps");
+    ui.add(egui::Slider::new(&mut sampler.lod_bias, -2.0..=2.0).text("LOD bias"));
+}
+
+pub fn clock_ui(ui: &mut egui::Ui, clock: &mut renderer::environment::WorldClock) {
+    ui.heading("Time");
+    ui.add(egui::Slider::new(&mut clock.time_of_day, 0.0..=1.0).text("Time of day"));
+    ui.add(egui::Slider::new(&mut clock.speed, 1.0..=1000.0).logarithmic(true).text("Speed"));
+    ui.checkbox(&mut clock.paused, "Paused");
+}
+
+pub fn fog_ui(ui: &mut egui::Ui, fog: &mut renderer::environment::FogSettings) {
+    use renderer::environment::FogMode;
+
+    ui.heading("Fog");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut fog.mode, FogMode::Linear, "Linear");
+        ui.radio_value(&mut fog.mode, FogMode::Exponential, "Exponential");
+    });
+    ui.add(egui::Slider::new(&mut fog.start, 0.0..=1.0).text("Start"));
+    ui.add(egui::Slider::new(&mut fog.underwater_density, 0.01..=0.5).text("Underwater density"));
+}
+
+pub fn shadow_ui(ui: &mut egui::Ui, shadow: &mut renderer::shadow::ShadowSettings) {
+    ui.heading("Shadows");
+    ui.checkbox(&mut shadow.enabled, "Enabled");
+    ui.add(egui::Slider::new(&mut shadow.cascades, 1..=renderer::shadow::MAX_CASCADES).text("Cascades"));
+    ui.add(egui::Slider::new(&mut shadow.distance, 16.0..=100.0).text("Distance"));
+    ui.add(egui::Slider::new(&mut shadow.split_lambda, 0.0..=1.0).text("Split lambda"));
+}
+
+pub fn shading_ui(ui: &mut egui::Ui, shading: &mut renderer::environment::ShadingSettings) {
+    ui.heading("Shading");
+    ui.checkbox(&mut shading.directional, "Directional face shading");
+    ui.add(egui::Slider::new(&mut shading.sun_lambert, 0.0..=1.0).text("Sun Lambert"));
+}
+
+pub fn cloud_ui(ui: &mut egui::Ui, cloud: &mut renderer::cloud::CloudSettings) {
+    ui.heading("Clouds");
+    ui.checkbox(&mut cloud.enabled, "Enabled");
+    ui.add(egui::Slider::new(&mut cloud.height, 8.0..=96.0).text("Height"));
+    ui.add(egui::Slider::new(&mut cloud.speed, 0.0..=20.0).text("Speed"));
+    ui.add(egui::Slider::new(&mut cloud.opacity, 0.0..=1.0).text("Opacity"));
+    ui.add(egui::Slider::new(&mut cloud.detail_distance, 0.0..=96.0).text("Detail distance"));
+}
+
+// Reflections read the depth buffer, which isn't resolved when multisampling, so they are off
+// with `sample_count` above 1.
+pub fn water_ui(ui: &mut egui::Ui, water: &mut renderer::water::WaterSettings, sample_count: u32) {
+    use renderer::water::WaterQuality;
+
+    ui.heading("Water");
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut water.quality, WaterQuality::Simple, "Simple");
+        let reflective = egui::RadioButton::new(water.quality == WaterQuality::Reflective, "Reflective");
+        let response = ui.add_enabled(sample_count == 1, reflective).on_disabled_hover_text("Needs MSAA off");
+        if response.clicked() {
+            water.quality = WaterQuality::Reflective;
+        }
+    });
+    ui.add(egui::Slider::new(&mut water.wave_height, 0.0..=0.1).text("Wave height"));
+    ui.add(egui::Slider::new(&mut water.wave_speed, 0.0..=4.0).text("Wave speed"));
+    ui.add(egui::Slider::new(&mut water.absorption, 0.05..=2.0).text("Absorption"));
+}
+
+pub fn post_ui(ui: &mut egui::Ui, post: &mut renderer::post::PostSettings) {
+    ui.heading("Post-processing");
+    ui.add(egui::Slider::new(&mut post.exposure, 0.1..=4.0).logarithmic(true).text("Exposure"));
+    ui.checkbox(&mut post.tone_mapping, "Tone mapping");
+    ui.checkbox(&mut post.bloom, "Bloom");
+    ui.add(egui::Slider::new(&mut post.bloom_threshold, 0.5..=4.0).text("Bloom threshold"));
+    ui.add(egui::Slider::new(&mut post.bloom_intensity, 0.0..=2.0).text("Bloom intensity"));
+    ui.checkbox(&mut post.fxaa, "FXAA");
+    ui.checkbox(&mut post.color_grading, "Colour grading");
+}
+
+pub fn path_ui(ui: &mut egui::Ui, path: &mut crate::path::CameraPath, camera: &renderer::camera::Camera) {
+    use crate::path::Interpolation;
+
+    ui.heading("Camera path");
+    ui.label(format!("{} keyframes", path.keyframes().len()));
+    ui.horizontal(|ui| {
+        if ui.button("Record (K)").clicked() { path.record(camera); }
+        if ui.button("Clear").clicked() { path.clear(); }
+        if ui.button("Save").clicked() { path.save(); }
+        if ui.button("Load").clicked() { path.load(); }
+    });
+    ui.horizontal(|ui| {
+        if path.playing {
+            if ui.button("Pause (P)").clicked() { path.playing = false; }
+        } else if ui.button("Play (P)").clicked() {
+            path.play();
+        }
+        if ui.button("Stop").clicked() { path.stop(); }
+    });
+    ui.horizontal(|ui| {
+        ui.radio_value(&mut path.interpolation, Interpolation::CatmullRom, "Catmull-Rom");
+        ui.radio_value(&mut path.interpolation, Interpolation::Bezier, "Bezier");
+    });
+    ui.add(egui::Slider::new(&mut path.speed, 0.5..=32.0).logarithmic(true).text("Speed"));
+
+    // Scrubbing takes over the camera, until stopped.
+    let duration = path.duration();
+    if ui.add_enabled(path.keyframes().len() >= 2, egui::Slider::new(&mut path.time, 0.0..=duration).text("Time")).changed() {
+        path.active = true;
+    }
+}
+
+pub fn screenshot_ui(ui: &mut egui::Ui, include_overlay: &mut bool) {
+    ui.heading("Screenshot (F2)");
+    ui.checkbox(include_overlay, "Include GUI and crosshair");
+}
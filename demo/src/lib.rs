@@ -1,6 +1,9 @@
 mod texture;
 mod control;
 mod gui;
+mod screenshot;
+mod path;
+mod settings;
 
 use std::collections::HashSet;
 use wgpu::util::DeviceExt;
@@ -20,6 +23,9 @@ pub struct SimpleChunk {
 }
 
 impl SimpleChunk {
+    const WATER: u32 = 3;
+    const LAMP: u32 = 4;
+
     pub fn new() -> Self {
         Self {
             data: Box::new([0; 100 * 16 * 16]),
@@ -57,6 +63,12 @@ impl SimpleChunk {
     }
 }
 
+impl Default for SimpleChunk {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl renderer::terrain::WorldInterface for SimpleChunk {
     fn get_block(&self, coord: &renderer::terrain::IntCoord) -> &renderer::terrain::BlockModel {
         const MODELS: &[renderer::terrain::BlockModel] = &[
@@ -66,13 +78,23 @@ impl renderer::terrain::WorldInterface for SimpleChunk {
             renderer::terrain::BlockModel {
                 faces: [renderer::terrain::TextureId::new(1); 6]
             },
+            renderer::terrain::BlockModel {
+                faces: [renderer::terrain::TextureId::new(2); 6]
+            },
+            renderer::terrain::BlockModel {
+                faces: [None; 6]
+            },
             renderer::terrain::BlockModel {
                 faces: [renderer::terrain::TextureId::new(2); 6]
             }
         ];
 
-        let coord = [coord.0[0] as usize, coord.0[1] as usize, coord.0[2] as usize];
-        let new = self.get(&coord);
+        let [x, y, z] = coord.0;
+        if !(0..16).contains(&x) || !(0..100).contains(&y) || !(0..16).contains(&z) {
+            return &MODELS[0];
+        }
+
+        let new = self.get(&[x as usize, y as usize, z as usize]);
         &MODELS[new as usize]
     }
 
@@ -83,6 +105,55 @@ impl renderer::terrain::WorldInterface for SimpleChunk {
     fn get_updated_block_coords(&self) -> &[renderer::terrain::IntCoord] {
         &self.updated
     }
+
+    fn is_water(&self, coord: &renderer::terrain::IntCoord) -> bool {
+        let [x, y, z] = coord.0;
+        (0..16).contains(&x) && (0..100).contains(&y) && (0..16).contains(&z)
+            && self.get(&[x as usize, y as usize, z as usize]) == SimpleChunk::WATER
+    }
+
+    fn get_emission(&self, coord: &renderer::terrain::IntCoord) -> u8 {
+        let [x, y, z] = coord.0;
+        let lamp = (0..16).contains(&x) && (0..100).contains(&y) && (0..16).contains(&z)
+            && self.get(&[x as usize, y as usize, z as usize]) == SimpleChunk::LAMP;
+        if lamp { renderer::terrain::MAX_LIGHT } else { 0 }
+    }
+}
+
+// A 64x64 skin with the given shirt colour, for `renderer::entity::EntityModel::humanoid`.
+fn humanoid_skin(shirt: [u8; 3]) -> Vec<u8> {
+    const SKIN: [u8; 3] = [220, 170, 130];
+    const HAIR: [u8; 3] = [70, 45, 25];
+    const PANTS: [u8; 3] = [50, 50, 110];
+    const EYES: [u8; 3] = [30, 30, 60];
+
+    let size = renderer::entity::EntityRenderer::SKIN_SIZE as usize;
+    let mut data = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let color = match (x, y) {
+                (9 | 10 | 13 | 14, 12) => EYES,
+                (8..=15, 8..=9) | (_, 0..=7) if x < 32 => HAIR,
+                (0..=31, 0..=15) => SKIN,
+                (0..=15, 16..=31) | (16..=31, 48..=63) => PANTS,
+                (16..=55, 16..=23) | (32..=47, 48..=55) => shirt,
+                (16..=39, 16..=31) => shirt,
+                (40..=55, 16..=31) | (32..=47, 48..=63) => SKIN,
+                _ => [0, 0, 0]
+            };
+            data.extend_from_slice(&color);
+            data.push(255);
+        }
+    }
+    data
+}
+
+// A warmer and slightly more contrasted grading, for `renderer::post::PostProcessor::set_lut`.
+fn warm_lut(size: u32) -> Vec<u8> {
+    renderer::post::identity_lut(size).chunks(4).flat_map(|texel| {
+        let [r, g, b] = [0, 1, 2].map(|i| (texel[i] as f32 / 255.0 - 0.5) * 1.15 + 0.5);
+        [r * 1.08, g, b * 0.88, 1.0].map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }).collect()
 }
 
 struct State {
@@ -93,17 +164,44 @@ struct State {
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     depth_texture_view: wgpu::TextureView,
+    msaa_texture_view: Option<wgpu::TextureView>,
+    settings: settings::RenderSettings,
+    supported_sample_counts: Vec<u32>,
+    supported_present_modes: Vec<wgpu::PresentMode>,
     chunk: SimpleChunk,
     i: usize,
     scene: renderer::terrain::Scene,
-    texture_bind_group: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    terrain_texture: renderer::terrain::TerrainTexture,
     camera: renderer::camera::Camera,
+    projection: renderer::camera::Projection,
     camera_buffer: wgpu::Buffer,
     camera_control: control::CameraControl,
+    camera_path: path::CameraPath,
     camera_bind_group: wgpu::BindGroup,
-    render_pipeline: wgpu::RenderPipeline,
+    terrain_renderer: renderer::terrain::TerrainRenderer,
+    shadow_settings: renderer::shadow::ShadowSettings,
+    shadow_renderer: renderer::shadow::ShadowRenderer,
+    clock: renderer::environment::WorldClock,
+    environment: renderer::environment::Environment,
+    fog: renderer::environment::FogSettings,
+    underwater: bool,
+    water_settings: renderer::water::WaterSettings,
+    water_renderer: renderer::water::WaterRenderer,
+    shading: renderer::environment::ShadingSettings,
+    updated_at: instant::Instant,
+    sky_renderer: renderer::sky::SkyRenderer,
+    particle_system: renderer::particle::ParticleSystem,
+    entity_renderer: renderer::entity::EntityRenderer,
+    entities: Vec<renderer::entity::Entity>,
+    entity_time: f32,
+    cloud_settings: renderer::cloud::CloudSettings,
+    cloud_renderer: renderer::cloud::CloudRenderer,
     indicator_renderer: renderer::indicator::IndicatorRenderer,
-    gui_renderer: gui::GuiRenderer
+    post_settings: renderer::post::PostSettings,
+    post_processor: renderer::post::PostProcessor,
+    gui_renderer: gui::GuiRenderer,
+    screenshot: screenshot::Screenshot
 }
 
 impl State {
@@ -123,8 +221,15 @@ impl State {
             force_fallback_adapter: false
         }).await.unwrap();
 
+        // Needed for sample counts other than 1 and 4, and for culling the terrain on the GPU.
+        let features = adapter.features() & (
+            wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                | wgpu::Features::INDIRECT_FIRST_INSTANCE
+                | wgpu::Features::MULTI_DRAW_INDIRECT
+        );
+
         let (device, queue) = adapter.request_device(&wgpu::DeviceDescriptor {
-            features: wgpu::Features::empty(),
+            features,
             limits: if cfg!(target_arch = "wasm32") {
                 wgpu::Limits::downlevel_webgl2_defaults()
             } else {
@@ -133,6 +238,8 @@ impl State {
             label: None
         }, None).await.unwrap();
 
+        let settings = settings::RenderSettings::default();
+
         let surface_caps = surface.get_capabilities(&adapter);
         let surface_format = surface_caps.formats.iter()
             .copied()
@@ -143,21 +250,39 @@ impl State {
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
+            present_mode: settings.present_mode,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![]
         };
         surface.configure(&device, &config);
 
-        let depth_texture_view = texture::create_depth_texture(&device, &config, "[demo] Depth Texture");
+        // The scene is rendered into the HDR target of the post-processor, only the crosshair
+        // and the GUI are drawn directly to the surface.
+        let supported_sample_counts = texture::supported_sample_counts(&adapter, renderer::post::HDR_FORMAT, features);
+        let sample_count = settings.sample_count;
+        let (width, height) = settings.scaled_size(config.width, config.height, device.limits().max_texture_dimension_2d);
+
+        let depth_texture_view = texture::create_depth_texture(&device, width, height, sample_count, "[demo] Depth Texture");
+        let msaa_texture_view = texture::create_msaa_texture(&device, width, height, renderer::post::HDR_FORMAT, sample_count, "[demo] MSAA Texture");
 
         let mut chunk = SimpleChunk::new();
-        chunk.update(&[
+        let mut blocks = vec![
             ([1, 1, 1], 1),
             ([1, 1, 2], 2),
             ([2, 1, 1], 2),
             ([2, 1, 2], 1)
-        ]);
+        ];
+        // A walled pool of water, two blocks deep.
+        for x in 6..14 {
+            for z in 6..14 {
+                let wall = x == 6 || x == 13 || z == 6 || z == 13;
+                let inside = if wall { 2 } else { SimpleChunk::WATER };
+                blocks.extend([([x, 0, z], 1), ([x, 1, z], inside), ([x, 2, z], inside)]);
+            }
+        }
+        // Lamps on the corners of the pool.
+        blocks.extend([6, 13].into_iter().flat_map(|x| [6, 13].map(|z| ([x, 3, z], SimpleChunk::LAMP))));
+        chunk.update(&blocks);
         let mut scene = renderer::terrain::Scene::new(&device);
         scene.update(&device, &queue, &chunk);
 
@@ -166,26 +291,33 @@ impl State {
 
         let texture_bind_group_layout = renderer::terrain::Scene::texture_bind_group_layout(&device);
 
-        let texture_bind_group = renderer::terrain::Scene::create_texture(
+        let mut mipmap_generator = renderer::mipmap::MipmapGenerator::new();
+        let terrain_texture = renderer::terrain::Scene::create_texture(
             &device,
             &queue,
+            &mut mipmap_generator,
             &texture_bind_group_layout,
             texture_image.width,
             texture_image.height,
-            &texture_image.data
+            &texture_image.data,
+            &settings.filtering
         );
 
-        let camera = renderer::camera::Camera {
-            pos: (5.0, 5.0, 5.0).into(),
-            yaw: cgmath::Deg(-135.0),
-            pitch: cgmath::Deg(-45.0),
-            fovy: cgmath::Deg(45.0),
-            aspect: config.width as f32 / config.height as f32
+        let camera = renderer::camera::Camera::new(
+            (5.0, 5.0, 5.0).into(),
+            cgmath::Deg(-135.0),
+            cgmath::Deg(-45.0),
+            settings.fovy,
+            config.width as f32 / config.height as f32
+        );
+        let projection = renderer::camera::Projection {
+            far: settings.far(),
+            ..Default::default()
         };
 
         let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("[demo] Camera Buffer"),
-            contents: bytemuck::cast_slice(&[renderer::camera::CameraUniform::new(&camera)]),
+            contents: bytemuck::cast_slice(&[renderer::camera::CameraUniform::with_projection(&camera, &projection)]),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
         });
 
@@ -212,62 +344,97 @@ impl State {
             }]
         });
 
-        let camera_control = control::CameraControl::new();
+        let camera_control = control::CameraControl::new(&camera);
 
-        let shader = device.create_shader_module(wgpu::include_wgsl!("../../renderer/src/shaders/terrain.wgsl"));
+        let environment_bind_group_layout = renderer::environment::Environment::bind_group_layout(&device);
 
-        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("[demo] Render Pipeline Layout"),
-            bind_group_layouts: &[
-                &texture_bind_group_layout,
-                &camera_bind_group_layout
-            ],
-            push_constant_ranges: &[]
-        });
+        let environment = renderer::environment::Environment::new(&device, &environment_bind_group_layout);
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("[demo] Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[renderer::terrain::Scene::buffer_layout::<0>()]
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL
-                })]
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false
-            },
-            depth_stencil: Some(wgpu::DepthStencilState {
-                format: texture::DEPTH_FORMAT,
-                depth_write_enabled: true,
-                depth_compare: wgpu::CompareFunction::Less,
-                stencil: Default::default(),
-                bias: Default::default()
-            }),
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false
-            },
-            multiview: None
+        let shadow_settings = renderer::shadow::ShadowSettings::default();
+
+        let shadow_renderer = renderer::shadow::ShadowRenderer::new(&device, &shadow_settings);
+
+        let terrain_renderer = renderer::terrain::TerrainRenderer::new(
+            &device,
+            renderer::post::HDR_FORMAT,
+            texture::DEPTH_FORMAT,
+            sample_count,
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+            &environment_bind_group_layout,
+            shadow_renderer.bind_group_layout()
+        );
+
+        let sky_renderer = renderer::sky::SkyRenderer::new(&device, renderer::post::HDR_FORMAT, sample_count, &environment_bind_group_layout);
+
+        let mut particle_system = renderer::particle::ParticleSystem::new(
+            &device,
+            renderer::post::HDR_FORMAT,
+            texture::DEPTH_FORMAT,
+            sample_count,
+            &texture_bind_group_layout,
+            &camera_bind_group_layout,
+            &environment_bind_group_layout
+        );
+
+        particle_system.add_emitter(renderer::particle::Emitter {
+            origin: (2.0, 14.0, 2.0).into(),
+            extent: (4.0, 0.5, 4.0).into(),
+            rate: 3.0,
+            velocity: (0.0, -0.5, 0.0).into(),
+            spread: 0.2,
+            gravity: 0.2,
+            lifetime: 6.0,
+            size: 0.08,
+            texture: renderer::terrain::TextureId::new(1).unwrap()
         });
 
+        let mut entity_renderer = renderer::entity::EntityRenderer::new(
+            &device,
+            renderer::post::HDR_FORMAT,
+            texture::DEPTH_FORMAT,
+            sample_count,
+            &camera_bind_group_layout,
+            &environment_bind_group_layout
+        );
+
+        let humanoid = entity_renderer.add_model(&device, renderer::entity::EntityModel::humanoid()).unwrap();
+        let walk = entity_renderer.model(humanoid).animation("walk").unwrap();
+        let idle = entity_renderer.model(humanoid).animation("idle").unwrap();
+
+        // The last one is the player, shown in the third person camera modes.
+        let entities = [[60, 90, 170], [170, 60, 60], [70, 150, 70], [200, 170, 60]].into_iter().enumerate().map(|(i, shirt)| {
+            let skin = entity_renderer.add_skin(&queue, &humanoid_skin(shirt)).unwrap();
+            let mut entity = renderer::entity::Entity::new(humanoid, skin, (2.0, 1.0, 2.0).into());
+            entity.play(if i == 0 || i == 3 { idle } else { walk }, 1.0);
+            entity
+        }).collect();
+
+        let water_settings = renderer::water::WaterSettings::default();
+        let mut water_renderer = renderer::water::WaterRenderer::new(
+            &device,
+            renderer::post::HDR_FORMAT,
+            texture::DEPTH_FORMAT,
+            sample_count,
+            &environment_bind_group_layout
+        );
+        water_renderer.set_targets(&device, &depth_texture_view, width, height);
+
+        let cloud_renderer = renderer::cloud::CloudRenderer::new(
+            &device,
+            renderer::post::HDR_FORMAT,
+            texture::DEPTH_FORMAT,
+            sample_count,
+            &camera_bind_group_layout,
+            &environment_bind_group_layout
+        );
+
         let indicator_renderer = renderer::indicator::IndicatorRenderer::new(&device, config.format);
 
+        let mut post_processor = renderer::post::PostProcessor::new(&device, &queue, config.format, width, height);
+        let lut_size = renderer::post::DEFAULT_LUT_SIZE;
+        post_processor.set_lut(&device, &queue, lut_size, &warm_lut(lut_size));
+
         let gui_renderer = gui::GuiRenderer::new(&window, &device, &config);
 
         Self {
@@ -278,17 +445,47 @@ impl State {
             queue,
             config,
             depth_texture_view,
+            msaa_texture_view,
+            settings,
+            supported_sample_counts,
+            supported_present_modes: surface_caps.present_modes,
             chunk,
             i: 2,
             scene,
-            texture_bind_group,
+            texture_bind_group_layout,
+            terrain_texture,
             camera,
+            projection,
             camera_buffer,
             camera_bind_group,
             camera_control,
-            render_pipeline,
+            camera_path: path::CameraPath::new(),
+            terrain_renderer,
+            shadow_settings,
+            shadow_renderer,
+            clock: renderer::environment::WorldClock::new(0.1),
+            environment,
+            fog: renderer::environment::FogSettings {
+                render_distance: settings.render_distance,
+                ..Default::default()
+            },
+            underwater: false,
+            water_settings,
+            water_renderer,
+            shading: Default::default(),
+            updated_at: instant::Instant::now(),
+            sky_renderer,
+            particle_system,
+            entity_renderer,
+            entities,
+            entity_time: 0.0,
+            cloud_settings: Default::default(),
+            cloud_renderer,
             indicator_renderer,
-            gui_renderer
+            post_settings: Default::default(),
+            post_processor,
+            gui_renderer,
+            screenshot: screenshot::Screenshot::new()
         }
     }
 
@@ -299,12 +496,77 @@ impl State {
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
 
-            self.depth_texture_view = texture::create_depth_texture(&self.device, &self.config, "[demo] Depth Texture");
+            self.create_targets();
+            let (width, height) = self.render_size();
+            self.post_processor.resize(&self.device, width, height);
 
             self.camera.aspect = new_size.width as f32 / new_size.height as f32;
         }
     }
 
+    // The size of the targets the scene is drawn into, before post-processing.
+    fn render_size(&self) -> (u32, u32) {
+        self.settings.scaled_size(self.config.width, self.config.height, self.device.limits().max_texture_dimension_2d)
+    }
+
+    fn create_targets(&mut self) {
+        let (width, height) = self.render_size();
+        let sample_count = self.settings.sample_count;
+        self.depth_texture_view = texture::create_depth_texture(&self.device, width, height, sample_count, "[demo] Depth Texture");
+        self.msaa_texture_view = texture::create_msaa_texture(&self.device, width, height, renderer::post::HDR_FORMAT, sample_count, "[demo] MSAA Texture");
+        if sample_count == 1 {
+            self.water_renderer.set_targets(&self.device, &self.depth_texture_view, width, height);
+        }
+    }
+
+    // Only rebuilds what depends on the fields that changed. The field of view is set on the
+    // camera every frame instead, see `update`.
+    fn apply_settings(&mut self, settings: settings::RenderSettings) {
+        let old = std::mem::replace(&mut self.settings, settings);
+
+        if settings.present_mode != old.present_mode {
+            self.config.present_mode = settings.present_mode;
+            self.surface.configure(&self.device, &self.config);
+        }
+        if settings.render_distance != old.render_distance {
+            self.projection.far = settings.far();
+            self.fog.render_distance = settings.render_distance;
+        }
+
+        let resized = settings.render_scale != old.render_scale;
+        if settings.sample_count != old.sample_count {
+            self.set_sample_count(settings.sample_count);
+        } else if resized {
+            self.create_targets();
+        }
+        if resized {
+            let (width, height) = self.render_size();
+            self.post_processor.resize(&self.device, width, height);
+        }
+
+        self.terrain_texture.set_sampler_settings(&self.device, &self.texture_bind_group_layout, &settings.filtering);
+    }
+
+    // Expects `settings.sample_count` to be set already.
+    fn set_sample_count(&mut self, sample_count: u32) {
+        self.create_targets();
+
+        self.terrain_renderer.set_sample_count(&self.device, sample_count);
+        self.sky_renderer.set_sample_count(&self.device, sample_count);
+        self.particle_system.set_sample_count(&self.device, sample_count);
+        self.entity_renderer.set_sample_count(&self.device, sample_count);
+        self.water_renderer.set_sample_count(&self.device, sample_count);
+        self.cloud_renderer.set_sample_count(&self.device, sample_count);
+    }
+
+    fn set_reversed_z(&mut self, reversed_z: bool) {
+        self.terrain_renderer.set_reversed_z(&self.device, reversed_z);
+        self.particle_system.set_reversed_z(&self.device, reversed_z);
+        self.entity_renderer.set_reversed_z(&self.device, reversed_z);
+        self.water_renderer.set_reversed_z(&self.device, reversed_z);
+        self.cloud_renderer.set_reversed_z(&self.device, reversed_z);
+    }
+
     fn input(&mut self, event: &WindowEvent) -> bool {
         if let winit::event::WindowEvent::KeyboardInput {
             input: winit::event::KeyboardInput {
@@ -331,6 +593,11 @@ impl State {
                 winit::event::VirtualKeyCode::Key2 => {
                     if self.i <= 2 { return true; }
                     self.i -= 1;
+                    for coord in [[1, self.i, 1], [1, self.i, 2], [2, self.i, 1], [2, self.i, 2]] {
+                        let coord = renderer::terrain::IntCoord(coord.map(|v| v as i32));
+                        let block = renderer::terrain::WorldInterface::get_block(&self.chunk, &coord);
+                        self.particle_system.spawn_block_break(&coord, block);
+                    }
                     self.chunk.update(&[
                         ([1, self.i, 1], 0),
                         ([1, self.i, 2], 0),
@@ -340,6 +607,22 @@ impl State {
                     self.scene.update(&self.device, &self.queue, &self.chunk);
                     return true;
                 }
+                winit::event::VirtualKeyCode::K => {
+                    self.camera_path.record(&self.camera);
+                    return true;
+                }
+                winit::event::VirtualKeyCode::P => {
+                    if self.camera_path.playing {
+                        self.camera_path.playing = false;
+                    } else {
+                        self.camera_path.play();
+                    }
+                    return true;
+                }
+                winit::event::VirtualKeyCode::F2 => {
+                    self.screenshot.request();
+                    return true;
+                }
                 _ => {}
             }
         }
@@ -348,31 +631,125 @@ impl State {
     }
 
     fn update(&mut self) {
-        self.camera_control.update_camera(&mut self.camera);
-        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[renderer::camera::CameraUniform::new(&self.camera)]));
+        let now = instant::Instant::now();
+        let secs = (now - self.updated_at).as_secs_f32();
+        self.updated_at = now;
+
+        self.camera_control.update_camera(&mut self.camera, &self.chunk);
+        // A camera path playing back overrides the field of view, until it stops.
+        self.camera.fovy = self.settings.fovy;
+        self.camera_path.update(secs, &mut self.camera);
+        self.queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[renderer::camera::CameraUniform::with_projection(&self.camera, &self.projection)]));
+
+        self.clock.tick(secs);
+        let eye = self.camera.pos.map(|v| v.floor() as i32);
+        self.underwater = renderer::terrain::WorldInterface::is_water(&self.chunk, &renderer::terrain::IntCoord(eye.into()));
+        self.environment.update(&self.queue, &self.clock, &self.fog, self.underwater, &self.shading);
+        self.sky_renderer.update(&self.queue, &self.camera, &self.projection);
+        // Before the culling, which draws the ranges of the compacted chunks.
+        self.scene.compact(&self.device, &self.queue);
+        self.terrain_renderer.update(&self.device, &self.queue, &self.scene, &self.camera, &self.projection);
+        self.shadow_renderer.update(&self.device, &self.queue, &self.camera, &self.projection, self.clock.sun_direction(), &self.shadow_settings);
+        self.particle_system.update(&self.device, &self.queue, &self.chunk, secs);
+        self.update_entities(secs);
+        self.cloud_renderer.update(&self.device, &self.queue, &self.camera, secs, &self.cloud_settings);
+        self.water_renderer.update(&self.queue, &self.camera, &self.projection, secs, &self.water_settings);
+        self.post_processor.update(&self.queue, &self.post_settings);
+        self.screenshot.update(&self.device);
+
+        let mut settings = self.settings;
+        let reversed_z = self.projection.reversed_z;
+        let mut gpu_meshing = self.scene.gpu_meshing();
+        self.gui_renderer.run(&self.window, |ctx| {
+            egui::SidePanel::left("My Panel").show(ctx, |ui| {
+                gui::settings_ui(ui, &mut settings, &self.supported_sample_counts, &self.supported_present_modes);
+                gui::camera_ui(ui, &mut self.camera_control);
+                gui::projection_ui(ui, &mut self.projection);
+                gui::terrain_ui(ui, &mut gpu_meshing);
+                gui::clock_ui(ui, &mut self.clock);
+                gui::fog_ui(ui, &mut self.fog);
+                gui::shading_ui(ui, &mut self.shading);
+                gui::shadow_ui(ui, &mut self.shadow_settings);
+                gui::cloud_ui(ui, &mut self.cloud_settings);
+                gui::water_ui(ui, &mut self.water_settings, self.settings.sample_count);
+                gui::post_ui(ui, &mut self.post_settings);
+                gui::path_ui(ui, &mut self.camera_path, &self.camera);
+                gui::screenshot_ui(ui, &mut self.screenshot.include_overlay);
+            });
+        });
+
+        if settings != self.settings {
+            self.apply_settings(settings);
+        }
+        if reversed_z != self.projection.reversed_z {
+            self.set_reversed_z(self.projection.reversed_z);
+        }
+        // Stays off where compute shaders are missing.
+        if gpu_meshing != self.scene.gpu_meshing() {
+            self.scene.set_gpu_meshing(&self.device, gpu_meshing);
+        }
+    }
+
+    // The first entity stands on the pillar watching the camera, the player is placed below its
+    // eye and the others walk around the pillar.
+    fn update_entities(&mut self, secs: f32) {
+        self.entity_time += secs;
+
+        let player = self.entities.len() - 1;
+        for (i, entity) in self.entities.iter_mut().enumerate() {
+            if i == 0 {
+                entity.pos = (2.0, self.i as f32, 2.0).into();
+                entity.look_at(self.camera.pos);
+            } else if i == player {
+                let yaw: cgmath::Rad<f32> = self.camera_control.yaw.into();
+                let pitch: cgmath::Rad<f32> = self.camera_control.pitch.into();
+                entity.pos = self.camera_control.pos - cgmath::Vector3::unit_y() * renderer::entity::Entity::EYE_HEIGHT;
+                entity.yaw = std::f32::consts::FRAC_PI_2 - yaw.0;
+                entity.head_pitch = pitch.0;
+            } else {
+                let angle = self.entity_time * 0.4 + i as f32 * std::f32::consts::PI;
+                entity.pos = (2.0 + 4.0 * angle.cos(), 1.0, 2.0 + 4.0 * angle.sin()).into();
+                entity.yaw = -angle;
+            }
+            entity.tick(secs);
+        }
+
+        let visible = if self.camera_control.mode().shows_player() { &self.entities[..] } else { &self.entities[..player] };
+        self.entity_renderer.update(&self.device, &self.queue, &self.chunk, visible);
     }
 
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output.texture.create_view(&Default::default());
+        // The scene is drawn into the multisampled texture when there is one, and resolved into
+        // the HDR target before post-processing.
+        let hdr_view = self.post_processor.hdr_view();
+        let target = self.msaa_texture_view.as_ref().unwrap_or(hdr_view);
+        let screenshot = self.screenshot.take_request(&self.device, &self.config);
+        let screenshot_view = screenshot.as_ref().map(|texture| texture.create_view(&Default::default()));
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
             label: Some("[demo] Render Encoder")
         });
 
+        self.shadow_renderer.render(&mut encoder, &self.scene);
+        self.terrain_renderer.cull(&mut encoder);
+
+        self.sky_renderer.render(&mut encoder, target, &self.environment);
+
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("[demo] Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view: target,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Load,
                     store: wgpu::StoreOp::Store
                 }
             })],
             depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
                 view: &self.depth_texture_view,
                 depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.0),
+                    load: wgpu::LoadOp::Clear(self.projection.depth_clear_value()),
                     store: wgpu::StoreOp::Store
                 }),
                 stencil_ops: None
@@ -381,19 +758,56 @@ impl State {
             timestamp_writes: None
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+        render_pass.set_bind_group(0, self.terrain_texture.bind_group(), &[]);
         render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.scene.buffer().slice(..));
-        render_pass.draw(0..6, 0..self.scene.len() as u32);
+        render_pass.set_bind_group(2, self.environment.bind_group(), &[]);
+        render_pass.set_bind_group(3, self.shadow_renderer.bind_group(), &[]);
+        self.terrain_renderer.render(&mut render_pass, &self.scene);
+        self.particle_system.render(&mut render_pass);
+        self.entity_renderer.render(&mut render_pass);
 
         drop(render_pass);
 
-        self.indicator_renderer.render(&mut encoder, &view);
+        self.water_renderer.render(&mut encoder, self.post_processor.hdr_texture(), target, &self.depth_texture_view, &self.environment, &self.scene);
+
+        self.cloud_renderer.render(&mut encoder, target, &self.depth_texture_view, &self.camera_bind_group, &self.environment);
+
+        if let Some(msaa_view) = &self.msaa_texture_view {
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("[demo] Resolve Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: msaa_view,
+                    resolve_target: Some(hdr_view),
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Discard
+                    }
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None
+            });
+        }
 
-        self.gui_renderer.render(&self.window, &self.device, &self.queue, &self.config, &mut encoder, &view);
+        self.post_processor.render(&mut encoder, &view);
+        if let Some(screenshot_view) = &screenshot_view {
+            self.post_processor.render(&mut encoder, screenshot_view);
+        }
+
+        let overlay_views: Vec<_> = std::iter::once(&view)
+            .chain(screenshot_view.as_ref().filter(|_| self.screenshot.include_overlay))
+            .collect();
+
+        for view in &overlay_views {
+            self.indicator_renderer.render(&mut encoder, view);
+        }
+
+        self.gui_renderer.render(&self.device, &self.queue, &self.config, &mut encoder, &overlay_views);
 
         self.queue.submit(Some(encoder.finish()));
+        if let Some(screenshot) = &screenshot {
+            self.screenshot.read(&self.device, &self.queue, screenshot);
+        }
         output.present();
 
         Ok(())
@@ -442,22 +856,20 @@ pub async fn run() {
         Event::WindowEvent {
             window_id,
             event
-        } if window_id == state.window.id() => if !state.input(&event) {
-            match event {
-                WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
-                    input: KeyboardInput {
-                        state: ElementState::Pressed,
-                        virtual_keycode: Some(VirtualKeyCode::Escape),
-                        ..
-                    },
+        } if window_id == state.window.id() && !state.input(&event) => match event {
+            WindowEvent::CloseRequested | WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Escape),
                     ..
-                } => *control_flow = ControlFlow::Exit,
-                WindowEvent::Resized(physical_size) =>
-                    state.resize(physical_size),
-                WindowEvent::ScaleFactorChanged { new_inner_size, .. } =>
-                    state.resize(*new_inner_size),
-                _ => {}
-            }
+                },
+                ..
+            } => *control_flow = ControlFlow::Exit,
+            WindowEvent::Resized(physical_size) =>
+                state.resize(physical_size),
+            WindowEvent::ScaleFactorChanged { new_inner_size, .. } =>
+                state.resize(*new_inner_size),
+            _ => {}
         }
         Event::RedrawRequested(window_id) if window_id == state.window.id() => {
             state.update();
@@ -0,0 +1,241 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Interpolation {
+    // Passes through every keyframe.
+    CatmullRom,
+    // Passes through the first and last keyframe, the others only pull the curve towards them,
+    // for a smoother sweep.
+    Bezier
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub pos: cgmath::Point3<f32>,
+    pub yaw: cgmath::Deg<f32>,
+    pub pitch: cgmath::Deg<f32>,
+    pub fovy: cgmath::Deg<f32>
+}
+
+impl Keyframe {
+    pub fn from_camera(camera: &renderer::camera::Camera) -> Self {
+        Self {
+            pos: camera.pos,
+            yaw: camera.yaw,
+            pitch: camera.pitch,
+            fovy: camera.fovy
+        }
+    }
+
+    fn to_array(self) -> [f32; 6] {
+        [self.pos.x, self.pos.y, self.pos.z, self.yaw.0, self.pitch.0, self.fovy.0]
+    }
+
+    fn from_array([x, y, z, yaw, pitch, fovy]: [f32; 6]) -> Self {
+        Self {
+            pos: (x, y, z).into(),
+            yaw: cgmath::Deg(yaw),
+            pitch: cgmath::Deg(pitch),
+            fovy: cgmath::Deg(fovy)
+        }
+    }
+}
+
+// Records keyframes of the demo camera and plays them back, overriding `CameraControl` while
+// active. Saved as one line of `x y z yaw pitch fovy` per keyframe, to a file in the working
+// directory or to local storage on the web.
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+    pub interpolation: Interpolation,
+    // Blocks per second along the keyframes.
+    pub speed: f32,
+    // The camera follows the path at `time` while active, and `time` advances while playing.
+    pub active: bool,
+    pub playing: bool,
+    pub time: f32
+}
+
+impl CameraPath {
+    const FILE_NAME: &'static str = "camera_path.txt";
+    // Keeps keyframes at the same position, which only turn the camera, from taking no time.
+    const MIN_SEGMENT_LENGTH: f32 = 1.0;
+
+    pub fn new() -> Self {
+        Self {
+            keyframes: vec![],
+            interpolation: Interpolation::CatmullRom,
+            speed: 4.0,
+            active: false,
+            playing: false,
+            time: 0.0
+        }
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    pub fn record(&mut self, camera: &renderer::camera::Camera) {
+        self.keyframes.push(Keyframe::from_camera(camera));
+    }
+
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+        self.stop();
+    }
+
+    pub fn play(&mut self) {
+        if self.keyframes.len() < 2 { return; }
+        if self.time >= self.duration() {
+            self.time = 0.0;
+        }
+        self.active = true;
+        self.playing = true;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.playing = false;
+        self.time = 0.0;
+    }
+
+    fn segment_durations(&self) -> impl Iterator<Item = f32> + '_ {
+        use cgmath::MetricSpace;
+
+        self.keyframes.windows(2).map(|pair| {
+            pair[0].pos.distance(pair[1].pos).max(Self::MIN_SEGMENT_LENGTH) / self.speed
+        })
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.segment_durations().sum()
+    }
+
+    // Advances the playback by `secs` and moves `camera` onto the path if it is active.
+    pub fn update(&mut self, secs: f32, camera: &mut renderer::camera::Camera) {
+        if !self.active || self.keyframes.len() < 2 {
+            self.active = false;
+            return;
+        }
+
+        let duration = self.duration();
+        if self.playing {
+            self.time += secs;
+            if self.time >= duration {
+                self.playing = false;
+            }
+        }
+        self.time = self.time.clamp(0.0, duration);
+
+        let keyframe = self.sample(self.time);
+        camera.pos = keyframe.pos;
+        camera.yaw = keyframe.yaw;
+        camera.pitch = keyframe.pitch;
+        camera.fovy = keyframe.fovy;
+    }
+
+    pub fn sample(&self, time: f32) -> Keyframe {
+        let points: Vec<_> = self.keyframes.iter().map(|keyframe| keyframe.to_array()).collect();
+        let duration = self.duration();
+
+        let values = match self.interpolation {
+            Interpolation::CatmullRom => {
+                // The segment containing `time`, and how far into it.
+                let mut start = 0.0;
+                let mut segment = (points.len() - 2, 1.0);
+                for (i, length) in self.segment_durations().enumerate() {
+                    if time < start + length {
+                        segment = (i, (time - start) / length);
+                        break;
+                    }
+                    start += length;
+                }
+
+                let (i, t) = segment;
+                let get = |j: isize| points[(i as isize + j).clamp(0, points.len() as isize - 1) as usize];
+                catmull_rom(get(-1), get(0), get(1), get(2), t)
+            }
+            Interpolation::Bezier => bezier(&points, if duration > 0.0 { time / duration } else { 0.0 })
+        };
+
+        Keyframe::from_array(values)
+    }
+
+    pub fn save(&self) {
+        let text: String = self.keyframes.iter().map(|keyframe| {
+            let [x, y, z, yaw, pitch, fovy] = keyframe.to_array();
+            format!("{x} {y} {z} {yaw} {pitch} {fovy}\n")
+        }).collect();
+
+        match write_file(Self::FILE_NAME, &text) {
+            Ok(_) => log::info!("Saved {} keyframes to {}", self.keyframes.len(), Self::FILE_NAME),
+            Err(e) => log::error!("Failed to save camera path: {e}")
+        }
+    }
+
+    pub fn load(&mut self) {
+        let text = match read_file(Self::FILE_NAME) {
+            Ok(text) => text,
+            Err(e) => {
+                log::error!("Failed to load camera path: {e}");
+                return;
+            }
+        };
+
+        let keyframes: Option<Vec<_>> = text.lines().filter(|line| !line.trim().is_empty()).map(|line| {
+            let values: Vec<f32> = line.split_whitespace().map(|value| value.parse().ok()).collect::<Option<_>>()?;
+            Some(Keyframe::from_array(values.try_into().ok()?))
+        }).collect();
+
+        match keyframes {
+            Some(keyframes) => {
+                self.keyframes = keyframes;
+                self.stop();
+            }
+            None => log::error!("Failed to parse camera path {}", Self::FILE_NAME)
+        }
+    }
+}
+
+fn catmull_rom(p0: [f32; 6], p1: [f32; 6], p2: [f32; 6], p3: [f32; 6], t: f32) -> [f32; 6] {
+    let (t2, t3) = (t * t, t * t * t);
+    std::array::from_fn(|i| {
+        0.5 * (2.0 * p1[i]
+            + (p2[i] - p0[i]) * t
+            + (2.0 * p0[i] - 5.0 * p1[i] + 4.0 * p2[i] - p3[i]) * t2
+            + (3.0 * p1[i] - p0[i] - 3.0 * p2[i] + p3[i]) * t3)
+    })
+}
+
+// De Casteljau's algorithm over all points.
+fn bezier(points: &[[f32; 6]], t: f32) -> [f32; 6] {
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points.windows(2).map(|pair| std::array::from_fn(|i| pair[0][i] + (pair[1][i] - pair[0][i]) * t)).collect();
+    }
+    points[0]
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(target_arch = "wasm32")] {
+        fn storage() -> Result<web_sys::Storage, String> {
+            web_sys::window()
+                .and_then(|window| window.local_storage().ok().flatten())
+                .ok_or_else(|| "local storage is unavailable".to_string())
+        }
+
+        fn write_file(name: &str, text: &str) -> Result<(), String> {
+            storage()?.set_item(name, text).map_err(|e| format!("{e:?}"))
+        }
+
+        fn read_file(name: &str) -> Result<String, String> {
+            storage()?.get_item(name).map_err(|e| format!("{e:?}"))?.ok_or_else(|| format!("{name} is not saved"))
+        }
+    } else {
+        fn write_file(name: &str, text: &str) -> std::io::Result<()> {
+            std::fs::write(name, text)
+        }
+
+        fn read_file(name: &str) -> std::io::Result<String> {
+            std::fs::read_to_string(name)
+        }
+    }
+}
@@ -0,0 +1,135 @@
+// Captures frames to PNG files, or browser downloads on the web. A requested frame is drawn a
+// second time from the post-processing input into a copyable texture, as the surface usually
+// can't be copied from, and read back without blocking.
+pub struct Screenshot {
+    pub include_overlay: bool,
+    requested: bool,
+    pending: Vec<renderer::headless::Readback>
+}
+
+impl Screenshot {
+    pub fn new() -> Self {
+        Self {
+            include_overlay: false,
+            requested: false,
+            pending: vec![]
+        }
+    }
+
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    // The texture to draw the frame to, if one was requested since the last call.
+    pub fn take_request(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Option<wgpu::Texture> {
+        if !std::mem::take(&mut self.requested) { return None; }
+
+        Some(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[demo] Screenshot Texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[]
+        }))
+    }
+
+    // Call after the frame drawn to `texture` has been submitted.
+    pub fn read(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
+        self.pending.push(renderer::headless::Readback::new(device, queue, texture));
+    }
+
+    // Saves the screenshots whose readback is done.
+    pub fn update(&mut self, device: &wgpu::Device) {
+        if self.pending.is_empty() { return; }
+
+        device.poll(wgpu::Maintain::Poll);
+        self.pending.retain(|readback| {
+            let Some(result) = readback.try_finish() else { return true; };
+            match result {
+                Ok(image) => save(image),
+                Err(e) => log::error!("Failed to read back screenshot: {e}")
+            }
+            false
+        });
+    }
+}
+
+fn save(image: renderer::headless::Image) {
+    let name = format!("screenshot-{}.png", timestamp());
+
+    let Some(image) = image::RgbaImage::from_raw(image.width, image.height, image.data) else { return; };
+    let mut png = std::io::Cursor::new(vec![]);
+    if let Err(e) = image.write_to(&mut png, image::ImageOutputFormat::Png) {
+        log::error!("Failed to encode screenshot: {e}");
+        return;
+    }
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            if download(&name, png.get_ref()).is_none() {
+                log::error!("Failed to download screenshot");
+            }
+        } else {
+            match std::fs::write(&name, png.get_ref()) {
+                Ok(_) => log::info!("Saved screenshot to {name}"),
+                Err(e) => log::error!("Failed to save screenshot to {name}: {e}")
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn download(name: &str, data: &[u8]) -> Option<()> {
+    use wasm_bindgen::JsCast;
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+    let mut options = web_sys::BlobPropertyBag::new();
+    options.type_("image/png");
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).ok()?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).ok()?;
+
+    let anchor = web_sys::window()?
+        .document()?
+        .create_element("a").ok()?
+        .dyn_into::<web_sys::HtmlAnchorElement>().ok()?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+
+    web_sys::Url::revoke_object_url(&url).ok()
+}
+
+// The current UTC time as `YYYY-MM-DD_HH-MM-SS`.
+fn timestamp() -> String {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            let secs = (js_sys::Date::now() / 1000.0) as i64;
+        } else {
+            let secs = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs() as i64);
+        }
+    }
+
+    let (days, time) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Days since the epoch to a proleptic Gregorian date, counted in 400 year eras from March.
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{year:04}-{month:02}-{day:02}_{:02}-{:02}-{:02}", time / 3600, time / 60 % 60, time % 60)
+}
@@ -0,0 +1,42 @@
+// The choices that used to be fixed when the window was created. Changes are applied while
+// running by `State::apply_settings`, which only rebuilds what depends on the changed fields.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct RenderSettings {
+    pub present_mode: wgpu::PresentMode,
+    pub fovy: cgmath::Deg<f32>,
+    // Where the fog ends, the far plane is a little beyond it.
+    pub render_distance: f32,
+    pub sample_count: u32,
+    // The size of the scene targets relative to the surface, scaled to it by post-processing.
+    pub render_scale: f32,
+    pub filtering: renderer::terrain::SamplerSettings
+}
+
+impl RenderSettings {
+    // So that nothing is clipped before it is fully fogged.
+    const FAR_PLANE_MARGIN: f32 = 4.0;
+
+    pub fn far(&self) -> f32 {
+        self.render_distance + Self::FAR_PLANE_MARGIN
+    }
+
+    // The size of the scene targets for a surface of `width` by `height`.
+    pub fn scaled_size(&self, width: u32, height: u32, max_size: u32) -> (u32, u32) {
+        let scale = |v: u32| ((v as f32 * self.render_scale).round() as u32).clamp(1, max_size);
+        (scale(width), scale(height))
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            // Always supported.
+            present_mode: wgpu::PresentMode::Fifo,
+            fovy: cgmath::Deg(45.0),
+            render_distance: 96.0,
+            sample_count: 1,
+            render_scale: 1.0,
+            filtering: Default::default()
+        }
+    }
+}
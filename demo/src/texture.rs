@@ -21,16 +21,16 @@ impl Image {
 
 pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
 
-pub fn create_depth_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, label: &str) -> wgpu::TextureView {
+pub fn create_depth_texture(device: &wgpu::Device, width: u32, height: u32, sample_count: u32, label: &str) -> wgpu::TextureView {
     let texture = device.create_texture(&wgpu::TextureDescriptor {
         label: Some(label),
         size: wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1
         },
         mip_level_count: 1,
-        sample_count: 1,
+        sample_count,
         dimension: wgpu::TextureDimension::D2,
         format: DEPTH_FORMAT,
         usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -39,3 +39,39 @@ pub fn create_depth_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfigu
 
     texture.create_view(&Default::default())
 }
+
+// The colour target everything is drawn into before being resolved into the post-processing
+// input, if multisampling is enabled.
+pub fn create_msaa_texture(device: &wgpu::Device, width: u32, height: u32, format: wgpu::TextureFormat, sample_count: u32, label: &str) -> Option<wgpu::TextureView> {
+    if sample_count == 1 { return None; }
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[]
+    });
+
+    Some(texture.create_view(&Default::default()))
+}
+
+// Sample counts usable with both `format` and `DEPTH_FORMAT`. Without
+// `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES` only the counts WebGPU guarantees are allowed.
+pub fn supported_sample_counts(adapter: &wgpu::Adapter, format: wgpu::TextureFormat, features: wgpu::Features) -> Vec<u32> {
+    [1, 2, 4, 8].into_iter().filter(|&count| {
+        if !features.contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            return count == 1 || count == 4;
+        }
+        [format, DEPTH_FORMAT].iter().all(|&format| {
+            adapter.get_texture_format_features(format).flags.sample_count_supported(count)
+        })
+    }).collect()
+}
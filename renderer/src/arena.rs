@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::utils::{DynamicBuffer, ShrinkPolicy};
+
+// A mesh in a `MeshArena`. Ids of removed meshes are reused.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct MeshId(u32);
+
+// Where a mesh is, in elements of the arena's stride. Drawn with `arena.buffer(page)` bound as
+// the vertex buffer and `elements()` as the vertex or instance range.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MeshRange {
+    pub page: usize,
+    pub offset: u32,
+    pub count: u32
+}
+
+impl MeshRange {
+    pub fn elements(&self) -> Range<u32> {
+        self.offset..self.offset + self.count
+    }
+}
+
+struct Page {
+    buffer: DynamicBuffer,
+    // Sorted, and neither touching each other nor `end`.
+    free: Vec<Range<u32>>,
+    // Everything from here on is free.
+    end: u32,
+    meshes: BTreeMap<u32, MeshId>
+}
+
+impl Page {
+    // First fit, then from the end.
+    fn allocate(&mut self, count: u32, capacity: u32) -> Option<u32> {
+        if let Some(i) = self.free.iter().position(|block| block.len() as u32 >= count) {
+            let offset = self.free[i].start;
+            self.free[i].start += count;
+            if self.free[i].is_empty() {
+                self.free.remove(i);
+            }
+            return Some(offset);
+        }
+
+        (self.end + count <= capacity).then(|| {
+            self.end += count;
+            self.end - count
+        })
+    }
+
+    // Merges `block` with the free blocks around it.
+    fn release(&mut self, mut block: Range<u32>) {
+        let mut i = self.free.partition_point(|free| free.start < block.start);
+        if i < self.free.len() && self.free[i].start == block.end {
+            block.end = self.free.remove(i).end;
+        }
+        if i > 0 && self.free[i - 1].end == block.start {
+            i -= 1;
+            block.start = self.free.remove(i).start;
+        }
+
+        if block.end == self.end {
+            self.end = block.start;
+        } else {
+            self.free.insert(i, block);
+        }
+    }
+}
+
+// Suballocates meshes of `stride` sized elements from a few large buffers, the pages, instead of
+// a buffer per mesh. Removed meshes leave free blocks behind, which `compact` closes a bit at a
+// time by moving the meshes after them down, so that the pages can shrink again.
+pub struct MeshArena {
+    label: String,
+    stride: u32,
+    usage: wgpu::BufferUsages,
+    // In elements. Meshes larger than this get a page of their own.
+    page_capacity: u32,
+    pages: Vec<Page>,
+    meshes: Vec<Option<MeshRange>>,
+    free_ids: Vec<MeshId>,
+    // Moves go through here, as a buffer can't be copied to itself.
+    scratch: DynamicBuffer
+}
+
+impl MeshArena {
+    const MAX_PAGE_SIZE: wgpu::BufferAddress = 64 << 20;
+    const INITIAL_PAGE_ELEMENTS: wgpu::BufferAddress = 256;
+
+    pub fn new(device: &wgpu::Device, label: String, stride: u32, usage: wgpu::BufferUsages) -> Self {
+        assert!(stride > 0 && stride as wgpu::BufferAddress & (wgpu::COPY_BUFFER_ALIGNMENT - 1) == 0, "stride has to be a multiple of 4");
+
+        // Pages grow in powers of two from their initial size, which must not pass the limit.
+        let initial_size = Self::INITIAL_PAGE_ELEMENTS * stride as wgpu::BufferAddress;
+        let max_size = Self::MAX_PAGE_SIZE.min(device.limits().max_buffer_size);
+        let mut page_size = initial_size;
+        while page_size * 2 <= max_size { page_size *= 2; }
+
+        Self {
+            scratch: DynamicBuffer::new(device, format!("{label} Scratch"), initial_size, wgpu::BufferUsages::empty()),
+            label,
+            stride,
+            usage,
+            page_capacity: (page_size / stride as wgpu::BufferAddress) as u32,
+            pages: vec![],
+            meshes: vec![],
+            free_ids: vec![]
+        }
+    }
+
+    fn new_page(&self, device: &wgpu::Device) -> Page {
+        let mut buffer = DynamicBuffer::new(
+            device,
+            format!("{} Page {}", self.label, self.pages.len()),
+            Self::INITIAL_PAGE_ELEMENTS * self.stride as wgpu::BufferAddress,
+            self.usage
+        );
+        buffer.set_shrink_policy(ShrinkPolicy::BelowQuarter);
+
+        Page {
+            buffer,
+            free: vec![],
+            end: 0,
+            meshes: BTreeMap::new()
+        }
+    }
+
+    fn count(&self, data: &[u8]) -> u32 {
+        assert_eq!(data.len() % self.stride as usize, 0, "mesh data has to be a whole number of elements");
+        (data.len() / self.stride as usize) as u32
+    }
+
+    fn allocate(&mut self, device: &wgpu::Device, count: u32) -> MeshRange {
+        if count == 0 {
+            return MeshRange { page: 0, offset: 0, count };
+        }
+
+        let capacity = self.page_capacity;
+        let found = self.pages.iter_mut().enumerate()
+            .find_map(|(page, p)| p.allocate(count, capacity).map(|offset| (page, offset)));
+        let (page, offset) = found.unwrap_or_else(|| {
+            let mut p = self.new_page(device);
+            p.end = count;
+            self.pages.push(p);
+            (self.pages.len() - 1, 0)
+        });
+
+        MeshRange { page, offset, count }
+    }
+
+    fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, range: MeshRange, data: &[u8]) {
+        if range.count == 0 { return; }
+
+        let offset = range.offset as wgpu::BufferAddress * self.stride as wgpu::BufferAddress;
+        self.pages[range.page].buffer.write(device, queue, offset, data);
+    }
+
+    fn release(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, range: MeshRange) {
+        if range.count == 0 { return; }
+
+        let page = &mut self.pages[range.page];
+        page.release(range.elements());
+        page.buffer.shrink(device, queue, page.end as wgpu::BufferAddress * self.stride as wgpu::BufferAddress);
+    }
+
+    pub fn insert(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> MeshId {
+        let range = self.allocate(device, self.count(data));
+        self.write(device, queue, range, data);
+
+        let id = self.free_ids.pop().unwrap_or_else(|| {
+            self.meshes.push(None);
+            MeshId(self.meshes.len() as u32 - 1)
+        });
+        self.meshes[id.0 as usize] = Some(range);
+        if range.count > 0 {
+            self.pages[range.page].meshes.insert(range.offset, id);
+        }
+        id
+    }
+
+    // Stays in place if `data` isn't larger than the mesh was.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, id: MeshId, data: &[u8]) {
+        let count = self.count(data);
+        let old = self.get(id);
+
+        if count > 0 && count <= old.count {
+            let range = MeshRange { count, ..old };
+            self.write(device, queue, range, data);
+            self.meshes[id.0 as usize] = Some(range);
+            if count < old.count {
+                self.release(device, queue, MeshRange { offset: old.offset + count, count: old.count - count, ..old });
+            }
+            return;
+        }
+
+        if old.count > 0 {
+            self.pages[old.page].meshes.remove(&old.offset);
+            self.release(device, queue, old);
+        }
+        let range = self.allocate(device, count);
+        self.write(device, queue, range, data);
+        self.meshes[id.0 as usize] = Some(range);
+        if count > 0 {
+            self.pages[range.page].meshes.insert(range.offset, id);
+        }
+    }
+
+    pub fn remove(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, id: MeshId) {
+        let range = self.get(id);
+        if range.count > 0 {
+            self.pages[range.page].meshes.remove(&range.offset);
+            self.release(device, queue, range);
+        }
+        self.meshes[id.0 as usize] = None;
+        self.free_ids.push(id);
+    }
+
+    // Panics if `id` was removed. Ranges change when meshes are updated or compacted.
+    pub fn get(&self, id: MeshId) -> MeshRange {
+        self.meshes[id.0 as usize].expect("mesh was removed")
+    }
+
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    pub fn buffer(&self, page: usize) -> &wgpu::Buffer {
+        self.pages[page].buffer.buffer()
+    }
+
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    // Elements in free blocks between meshes, which `compact` would reclaim.
+    pub fn fragmented(&self) -> u32 {
+        self.pages.iter().flat_map(|page| &page.free).map(|block| block.len() as u32).sum()
+    }
+
+    // Moves meshes into the free blocks before them, about `budget` elements worth, so that it
+    // can be called every frame. At least one mesh is moved if there are free blocks. Returns
+    // whether any were left.
+    pub fn compact(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, budget: u32) -> bool {
+        let stride = self.stride as wgpu::BufferAddress;
+        let mut encoder: Option<wgpu::CommandEncoder> = None;
+        let mut moved = 0;
+        let mut compacted = vec![];
+
+        'pages: for (page_index, page) in self.pages.iter_mut().enumerate() {
+            while let Some(block) = page.free.first().cloned() {
+                // Free blocks don't touch the end or each other, so a mesh follows each.
+                let id = page.meshes[&block.end];
+                let range = self.meshes[id.0 as usize].as_mut().unwrap();
+                if moved > 0 && moved + range.count > budget { break 'pages; }
+
+                let size = range.count as wgpu::BufferAddress * stride;
+                self.scratch.reserve(device, queue, size);
+                let encoder = encoder.get_or_insert_with(|| device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("[arena] Compact Encoder")
+                }));
+                encoder.copy_buffer_to_buffer(page.buffer.buffer(), block.end as wgpu::BufferAddress * stride, self.scratch.buffer(), 0, size);
+                encoder.copy_buffer_to_buffer(self.scratch.buffer(), 0, page.buffer.buffer(), block.start as wgpu::BufferAddress * stride, size);
+
+                page.free.remove(0);
+                page.meshes.remove(&block.end);
+                page.meshes.insert(block.start, id);
+                range.offset = block.start;
+                page.release(block.start + range.count..block.end + range.count);
+
+                moved += range.count;
+                if compacted.last() != Some(&page_index) {
+                    compacted.push(page_index);
+                }
+            }
+        }
+
+        if let Some(encoder) = encoder {
+            queue.submit(Some(encoder.finish()));
+        }
+        // Only after the moves are submitted, as shrinking copies the buffer.
+        for page_index in compacted {
+            let page = &mut self.pages[page_index];
+            page.buffer.shrink(device, queue, page.end as wgpu::BufferAddress * stride);
+        }
+
+        self.pages.iter().any(|page| !page.free.is_empty())
+    }
+}
@@ -1,3 +1,195 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectionMode {
+    Perspective,
+    // For map and isometric views, `fovy` is ignored.
+    Orthographic
+}
+
+// Held next to a `Camera` rather than in it, and passed along wherever depth or the view
+// volume matter. `Camera` alone uses the default.
+#[derive(Clone, Copy, Debug)]
+pub struct Projection {
+    pub mode: ProjectionMode,
+    pub near: f32,
+    pub far: f32,
+    // Orthographic views are `ORTHO_HEIGHT / zoom` blocks high.
+    pub zoom: f32,
+    // Depth 1 at the near plane and 0 at the far plane, which is at infinity in perspective
+    // mode, for better precision far away. Depth buffers have to be cleared to
+    // `depth_clear_value` and the renderers given `reversed_z` to test with `Greater`.
+    pub reversed_z: bool
+}
+
+impl Projection {
+    pub const ORTHO_HEIGHT: f32 = 32.0;
+    // Maps depth z to 1 - z.
+    const REVERSE_Z_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, -1.0, 0.0,
+        0.0, 0.0, 1.0, 1.0,
+    );
+
+    pub fn depth_clear_value(&self) -> f32 {
+        if self.reversed_z { 0.0 } else { 1.0 }
+    }
+
+    // Half the width and height of the view at distance `d`.
+    pub(crate) fn half_extent(&self, fovy: cgmath::Deg<f32>, aspect: f32, d: f32) -> (f32, f32) {
+        use cgmath::Angle;
+
+        let half_height = match self.mode {
+            ProjectionMode::Perspective => (fovy / 2.0).tan() * d,
+            ProjectionMode::Orthographic => Self::ORTHO_HEIGHT / self.zoom / 2.0
+        };
+        (half_height * aspect, half_height)
+    }
+
+    pub fn get_proj_mat(&self, camera: &Camera) -> cgmath::Matrix4<f32> {
+        let Self { mode, near, far, reversed_z, .. } = *self;
+
+        match mode {
+            ProjectionMode::Perspective if reversed_z => {
+                // Depth is near / distance, reaching 0 at infinity.
+                let f = 1.0 / cgmath::Angle::tan(camera.fovy / 2.0);
+                cgmath::Matrix4::new(
+                    f / camera.aspect, 0.0, 0.0, 0.0,
+                    0.0, f, 0.0, 0.0,
+                    0.0, 0.0, 0.0, -1.0,
+                    0.0, 0.0, near, 0.0,
+                )
+            }
+            ProjectionMode::Perspective => Camera::OPENGL_TO_WGPU_MATRIX * cgmath::perspective(camera.fovy, camera.aspect, near, far),
+            ProjectionMode::Orthographic => {
+                let (half_width, half_height) = self.half_extent(camera.fovy, camera.aspect, 1.0);
+                let proj_mat = Camera::OPENGL_TO_WGPU_MATRIX * cgmath::ortho(-half_width, half_width, -half_height, half_height, near, far);
+                if reversed_z { Self::REVERSE_Z_MATRIX * proj_mat } else { proj_mat }
+            }
+        }
+    }
+
+    pub fn get_inv_proj_mat(&self, camera: &Camera) -> cgmath::Matrix4<f32> {
+        use cgmath::SquareMatrix;
+
+        self.get_proj_mat(camera).invert().unwrap_or(cgmath::Matrix4::identity())
+    }
+
+    pub fn get_view_proj_mat(&self, camera: &Camera) -> cgmath::Matrix4<f32> {
+        self.get_proj_mat(camera) * camera.get_view_mat()
+    }
+
+    pub fn get_inv_view_proj_mat(&self, camera: &Camera) -> cgmath::Matrix4<f32> {
+        use cgmath::SquareMatrix;
+
+        self.get_view_proj_mat(camera).invert().unwrap_or(cgmath::Matrix4::identity())
+    }
+
+    pub fn frustum(&self, camera: &Camera) -> Frustum {
+        Frustum::from_view_proj_mat(self.get_view_proj_mat(camera))
+    }
+
+    // The ray through pixel coordinates (`px`, `py`) of a `width` by `height` view, starting at
+    // the near plane. Pixel centers are at half coordinates.
+    pub fn screen_to_ray(&self, camera: &Camera, px: f32, py: f32, width: f32, height: f32) -> Ray {
+        use cgmath::{InnerSpace, Transform};
+
+        let inv_view_proj_mat = self.get_inv_view_proj_mat(camera);
+        let (x, y) = (px / width * 2.0 - 1.0, 1.0 - py / height * 2.0);
+        // Depth 0.5 is at a finite distance, also with reversed infinite projections.
+        let near_depth = if self.reversed_z { 1.0 } else { 0.0 };
+        let near = inv_view_proj_mat.transform_point(cgmath::Point3::new(x, y, near_depth));
+        let mid = inv_view_proj_mat.transform_point(cgmath::Point3::new(x, y, 0.5));
+
+        Ray {
+            origin: near,
+            dir: (mid - near).normalize()
+        }
+    }
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Self {
+            mode: ProjectionMode::Perspective,
+            near: Camera::Z_NEAR,
+            far: Camera::Z_FAR,
+            zoom: 1.0,
+            reversed_z: false
+        }
+    }
+}
+
+// Points `p` with `normal · p + distance >= 0` are inside.
+#[derive(Clone, Copy, Debug)]
+pub struct Plane {
+    pub normal: cgmath::Vector3<f32>,
+    pub distance: f32
+}
+
+impl Plane {
+    fn from_row(row: cgmath::Vector4<f32>) -> Self {
+        use cgmath::InnerSpace;
+
+        let normal = row.truncate();
+        // The far plane of reversed infinite projections has no normal and contains everything.
+        let len = normal.magnitude();
+        let len = if len > 0.0 { len } else { 1.0 };
+        Self {
+            normal: normal / len,
+            distance: row.w / len
+        }
+    }
+
+    pub fn signed_distance(&self, point: cgmath::Point3<f32>) -> f32 {
+        use cgmath::{EuclideanSpace, InnerSpace};
+
+        self.normal.dot(point.to_vec()) + self.distance
+    }
+}
+
+// The left, right, bottom and top planes of a camera, then those at depth 0 and 1, which are
+// the near and far plane or, with reversed depth, the other way around. All face inwards.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [Plane; 6]
+}
+
+impl Frustum {
+    pub fn from_view_proj_mat(mat: cgmath::Matrix4<f32>) -> Self {
+        use cgmath::Matrix;
+
+        let [x, y, z, w] = [0, 1, 2, 3].map(|i| mat.row(i));
+        // Clip space depth is in [0, 1], so the near plane is z >= 0 rather than z >= -w.
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(Plane::from_row)
+        }
+    }
+
+    pub fn contains_sphere(&self, center: cgmath::Point3<f32>, radius: f32) -> bool {
+        self.planes.iter().all(|plane| plane.signed_distance(center) >= -radius)
+    }
+
+    // Conservative, boxes near the edges may pass without intersecting.
+    pub fn contains_aabb(&self, min: cgmath::Point3<f32>, max: cgmath::Point3<f32>) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner farthest along the normal.
+            let corner = cgmath::Point3::new(
+                if plane.normal.x >= 0.0 { max.x } else { min.x },
+                if plane.normal.y >= 0.0 { max.y } else { min.y },
+                if plane.normal.z >= 0.0 { max.z } else { min.z }
+            );
+            plane.signed_distance(corner) >= 0.0
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: cgmath::Point3<f32>,
+    // Normalized.
+    pub dir: cgmath::Vector3<f32>
+}
+
 pub struct Camera {
     pub pos: cgmath::Point3<f32>,
     pub yaw: cgmath::Deg<f32>,
@@ -7,42 +199,81 @@ pub struct Camera {
 }
 
 impl Camera {
-    const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
+    pub(crate) const OPENGL_TO_WGPU_MATRIX: cgmath::Matrix4<f32> = cgmath::Matrix4::new(
         1.0, 0.0, 0.0, 0.0,
         0.0, 1.0, 0.0, 0.0,
         0.0, 0.0, 0.5, 0.0,
         0.0, 0.0, 0.5, 1.0,
     );
+    pub const Z_NEAR: f32 = 0.1;
+    pub const Z_FAR: f32 = 100.0;
 
-    pub fn get_view_proj_mat(&self) -> cgmath::Matrix4<f32> {
+    pub fn new(pos: cgmath::Point3<f32>, yaw: cgmath::Deg<f32>, pitch: cgmath::Deg<f32>, fovy: cgmath::Deg<f32>, aspect: f32) -> Self {
+        Self {
+            pos,
+            yaw,
+            pitch,
+            fovy,
+            aspect
+        }
+    }
+
+    pub fn forward(&self) -> cgmath::Vector3<f32> {
         use cgmath::Angle;
 
         let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
         let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
-        let facing = cgmath::Vector3::new(
+        cgmath::Vector3::new(
             cos_yaw * cos_pitch,
             sin_pitch,
             sin_yaw * cos_pitch
-        );
+        )
+    }
+
+    // Horizontal, so it stays defined when looking straight up or down.
+    pub fn right(&self) -> cgmath::Vector3<f32> {
+        use cgmath::Angle;
+
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        cgmath::Vector3::new(-sin_yaw, 0.0, cos_yaw)
+    }
+
+    pub fn up(&self) -> cgmath::Vector3<f32> {
+        self.right().cross(self.forward())
+    }
+
+    pub fn get_view_mat(&self) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::look_to_rh(self.pos, self.forward(), cgmath::Vector3::unit_y())
+    }
 
-        let view_mat = cgmath::Matrix4::look_to_rh(self.pos, facing, cgmath::Vector3::unit_y());
+    pub fn get_inv_view_mat(&self) -> cgmath::Matrix4<f32> {
+        use cgmath::SquareMatrix;
 
-        let proj_mat = cgmath::perspective(self.fovy, self.aspect, 0.1, 100.0);
+        self.get_view_mat().invert().unwrap_or(cgmath::Matrix4::identity())
+    }
 
-        return Self::OPENGL_TO_WGPU_MATRIX * proj_mat * view_mat;
+    // With the default projection, see `Projection::get_view_proj_mat` for others.
+    pub fn get_view_proj_mat(&self) -> cgmath::Matrix4<f32> {
+        Projection::default().get_view_proj_mat(self)
     }
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
 pub struct CameraUniform {
-    view_proj_mat: [[f32; 4]; 4]
+    view_proj_mat: [[f32; 4]; 4],
+    pos: [f32; 4]
 }
 
 impl CameraUniform {
     pub fn new(camera: &Camera) -> Self {
+        Self::with_projection(camera, &Default::default())
+    }
+
+    pub fn with_projection(camera: &Camera, projection: &Projection) -> Self {
         Self {
-            view_proj_mat: camera.get_view_proj_mat().into()
+            view_proj_mat: projection.get_view_proj_mat(camera).into(),
+            pos: [camera.pos.x, camera.pos.y, camera.pos.z, 1.0]
         }
     }
 }
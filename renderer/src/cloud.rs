@@ -0,0 +1,371 @@
+pub struct CloudSettings {
+    pub enabled: bool,
+    pub height: f32,
+    pub thickness: f32,
+    pub cell_size: f32,
+    pub opacity: f32,
+    // Drift speed along +x, in blocks per second.
+    pub speed: f32,
+    pub distance: f32,
+    // Cells closer than this are drawn as boxes, the rest as flat quads.
+    pub detail_distance: f32
+}
+
+impl Default for CloudSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            height: 48.0,
+            thickness: 4.0,
+            cell_size: 12.0,
+            opacity: 0.8,
+            speed: 1.0,
+            distance: 96.0,
+            detail_distance: 48.0
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct CloudUniform {
+    params: [f32; 4]
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct CloudCell {
+    origin: [f32; 2],
+    neighbors: u32
+}
+
+pub struct CloudMap {
+    size: usize,
+    cells: Vec<bool>
+}
+
+impl CloudMap {
+    // Thresholded, tiling value noise, so the map can repeat endlessly while it scrolls. Any size
+    // tiles, the lattice of each octave is stretched over the whole map.
+    pub fn generate(size: usize, seed: u32, coverage: f32) -> Self {
+        let size = size.max(1);
+        fn hash(x: u32, y: u32, seed: u32) -> f32 {
+            let mut h = x.wrapping_mul(0x27d4eb2d) ^ y.wrapping_mul(0x165667b1) ^ seed.wrapping_mul(0x9e3779b9);
+            h ^= h >> 15;
+            h = h.wrapping_mul(0x2c1b3c6d);
+            h ^= h >> 12;
+            (h & 0xffff) as f32 / 65535.0
+        }
+
+        let noise = |x: usize, y: usize, period: usize, seed: u32| {
+            // In lattice cells, from 0 up to `period`.
+            let scale = period as f32 / size as f32;
+            let (u, v) = (x as f32 * scale, y as f32 * scale);
+            let (gx, gy) = (u as usize, v as usize);
+            let (fx, fy) = (u.fract(), v.fract());
+            let corner = |dx: usize, dy: usize| hash(((gx + dx) % period) as u32, ((gy + dy) % period) as u32, seed);
+            let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * fx;
+            let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * fx;
+            top + (bottom - top) * fy
+        };
+
+        let cells = (0..size * size).map(|i| {
+            let (x, y) = (i % size, i / size);
+            let value = noise(x, y, 8, seed) * 0.65 + noise(x, y, 32, seed + 1) * 0.35;
+            value < coverage
+        }).collect();
+
+        Self { size, cells }
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> bool {
+        let size = self.size as i32;
+        self.cells[(y.rem_euclid(size) * size + x.rem_euclid(size)) as usize]
+    }
+}
+
+pub struct CloudRenderer {
+    box_pipeline: wgpu::RenderPipeline,
+    flat_pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    sample_count: u32,
+    reversed_z: bool,
+    map: CloudMap,
+    cells: Vec<CloudCell>,
+    box_count: u32,
+    scroll: f32,
+    enabled: bool,
+    instance_buffer: crate::utils::DynamicBuffer,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+impl CloudRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment_layout: &wgpu::BindGroupLayout
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[cloud] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/face.wgsl"),
+                include_str!("shaders/environment.wgsl"),
+                include_str!("shaders/cloud.wgsl")
+            ).into())
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[cloud] Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[cloud] Render Pipeline Layout"),
+            bind_group_layouts: &[
+                camera_layout,
+                environment_layout,
+                &bind_group_layout
+            ],
+            push_constant_ranges: &[]
+        });
+
+        let (box_pipeline, flat_pipeline) = Self::create_pipelines(device, &pipeline_layout, &shader, format, depth_format, sample_count, false);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[cloud] Uniform Buffer"),
+            size: std::mem::size_of::<CloudUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[cloud] Uniform Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding()
+            }]
+        });
+
+        Self {
+            box_pipeline,
+            flat_pipeline,
+            pipeline_layout,
+            shader,
+            format,
+            depth_format,
+            sample_count,
+            reversed_z: false,
+            map: CloudMap::generate(64, 0, 0.4),
+            cells: vec![],
+            box_count: 0,
+            scroll: 0.0,
+            enabled: false,
+            instance_buffer: crate::utils::DynamicBuffer::new(
+                device,
+                "[cloud] Cell Instance Buffer".into(),
+                512,
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
+            ),
+            buffer,
+            bind_group
+        }
+    }
+
+    // Returns the box and the flat pipeline.
+    fn create_pipelines(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        reversed_z: bool
+    ) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+        let create_pipeline = |entry_point, cull_mode| device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[cloud] Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point,
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<CloudCell>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Uint32]
+                }]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: if reversed_z { wgpu::CompareFunction::GreaterEqual } else { wgpu::CompareFunction::LessEqual },
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None
+        });
+
+        (create_pipeline("vs_box", Some(wgpu::Face::Back)), create_pipeline("vs_flat", None))
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.sample_count = sample_count;
+        (self.box_pipeline, self.flat_pipeline) = Self::create_pipelines(
+            device,
+            &self.pipeline_layout,
+            &self.shader,
+            self.format,
+            self.depth_format,
+            sample_count,
+            self.reversed_z
+        );
+    }
+
+    // Has to match `camera::Projection::reversed_z` of the rendered camera.
+    pub fn set_reversed_z(&mut self, device: &wgpu::Device, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+        (self.box_pipeline, self.flat_pipeline) = Self::create_pipelines(
+            device,
+            &self.pipeline_layout,
+            &self.shader,
+            self.format,
+            self.depth_format,
+            self.sample_count,
+            reversed_z
+        );
+    }
+
+    pub fn set_map(&mut self, map: CloudMap) {
+        self.map = map;
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, camera: &crate::camera::Camera, secs: f32, settings: &CloudSettings) {
+        self.enabled = settings.enabled;
+        if !self.enabled { return; }
+
+        let cell_size = settings.cell_size;
+        let period = self.map.size as f32 * cell_size;
+        self.scroll = (self.scroll + secs * settings.speed).rem_euclid(period);
+
+        let range = (settings.distance / cell_size).ceil() as i32;
+        let center_x = ((camera.pos.x - self.scroll) / cell_size).floor() as i32;
+        let center_z = (camera.pos.z / cell_size).floor() as i32;
+
+        // From the camera to the center of a cell.
+        let distance = |x: i32, z: i32| {
+            let dx = (x as f32 + 0.5) * cell_size + self.scroll - camera.pos.x;
+            let dz = (z as f32 + 0.5) * cell_size - camera.pos.z;
+            (dx * dx + dz * dz).sqrt()
+        };
+
+        let mut boxes = vec![];
+        let mut flats = vec![];
+
+        for z in center_z - range..=center_z + range {
+            for x in center_x - range..=center_x + range {
+                if !self.map.get(x, z) { continue; }
+
+                let origin = [x as f32 * cell_size + self.scroll, z as f32 * cell_size];
+                let dist = distance(x, z);
+                if dist > settings.distance + cell_size { continue; }
+
+                // Sides next to a flat cell are kept, as it has no side of its own to close the gap.
+                let neighbors = [(-1, 0, 0), (1, 0, 1), (0, -1, 4), (0, 1, 5)].iter()
+                    .filter(|(nx, nz, _)| self.map.get(x + nx, z + nz) && distance(x + nx, z + nz) < settings.detail_distance)
+                    .fold(0, |mask, (_, _, dir)| mask | 1 << dir);
+
+                let cell = CloudCell { origin, neighbors };
+                if dist < settings.detail_distance { boxes.push(cell); } else { flats.push(cell); }
+            }
+        }
+
+        self.box_count = boxes.len() as u32;
+        self.cells = boxes;
+        self.cells.append(&mut flats);
+
+        if !self.cells.is_empty() {
+            self.instance_buffer.update(device, queue, bytemuck::cast_slice(&self.cells));
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[CloudUniform {
+            params: [settings.height, settings.thickness, cell_size, settings.opacity]
+        }]));
+    }
+
+    // Draws over an already rendered frame, testing against its depth buffer.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        camera_bind_group: &wgpu::BindGroup,
+        environment: &crate::environment::Environment
+    ) {
+        if !self.enabled || self.cells.is_empty() { return; }
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("[cloud] Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store
+                }),
+                stencil_ops: None
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None
+        });
+
+        rpass.set_bind_group(0, camera_bind_group, &[]);
+        rpass.set_bind_group(1, environment.bind_group(), &[]);
+        rpass.set_bind_group(2, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.instance_buffer.buffer().slice(..));
+
+        rpass.set_pipeline(&self.flat_pipeline);
+        rpass.draw(0..6, self.box_count..self.cells.len() as u32);
+
+        rpass.set_pipeline(&self.box_pipeline);
+        rpass.draw(0..36, 0..self.box_count);
+    }
+}
@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ChunkBounds {
+    min: [f32; 3],
+    first_instance: u32,
+    max: [f32; 3],
+    instance_count: u32
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct CullUniform {
+    planes: [[f32; 4]; 6],
+    chunk_count: u32,
+    _padding: [u32; 3]
+}
+
+// The compute pass writing a `wgpu::util::DrawIndirect` per chunk, with no instances if it is
+// culled.
+struct GpuCuller {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    bounds_buffer: crate::utils::DynamicBuffer,
+    indirect_buffer: crate::utils::DynamicBuffer,
+    bind_group: Option<wgpu::BindGroup>,
+    multi_draw: bool
+}
+
+// Culls the chunks of a `terrain::Scene` against the camera frustum. On the GPU when compute
+// shaders and indirect draws with a first instance are available, drawing a page of the face
+// arena with one `multi_draw_indirect` if that is too, and otherwise on the CPU, as on WebGL.
+pub struct ChunkCuller {
+    gpu: Option<GpuCuller>,
+    // Sorted by page, `pages` holds the range of each.
+    chunks: Vec<ChunkBounds>,
+    pages: Vec<Range<u32>>,
+    revision: Option<u64>,
+    // Only used without the GPU path.
+    visible: Vec<bool>
+}
+
+impl ChunkCuller {
+    const WORKGROUP_SIZE: u32 = 64;
+    const DRAW_SIZE: wgpu::BufferAddress = std::mem::size_of::<wgpu::util::DrawIndirect>() as wgpu::BufferAddress;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        let limits = device.limits();
+        let supported = device.features().contains(wgpu::Features::INDIRECT_FIRST_INSTANCE)
+            && limits.max_compute_invocations_per_workgroup >= Self::WORKGROUP_SIZE
+            && limits.max_storage_buffers_per_shader_stage >= 2;
+
+        Self {
+            gpu: supported.then(|| Self::create_gpu_culler(device)),
+            chunks: vec![],
+            pages: vec![],
+            revision: None,
+            visible: vec![]
+        }
+    }
+
+    fn create_gpu_culler(device: &wgpu::Device) -> GpuCuller {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/cull.wgsl"));
+
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[cull] Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                },
+                storage_entry(1, true),
+                storage_entry(2, false)
+            ]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[cull] Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("[cull] Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main"
+        });
+
+        let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[cull] Uniform Buffer"),
+            size: std::mem::size_of::<CullUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        GpuCuller {
+            pipeline,
+            bind_group_layout,
+            uniform_buffer,
+            bounds_buffer: crate::utils::DynamicBuffer::new(
+                device,
+                "[cull] Chunk Bounds Buffer".into(),
+                1024,
+                wgpu::BufferUsages::STORAGE
+            ),
+            indirect_buffer: crate::utils::DynamicBuffer::new(
+                device,
+                "[cull] Indirect Buffer".into(),
+                512,
+                wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT
+            ),
+            bind_group: None,
+            multi_draw: device.features().contains(wgpu::Features::MULTI_DRAW_INDIRECT)
+        }
+    }
+
+    // Whether culling runs in a compute pass.
+    pub fn is_gpu(&self) -> bool {
+        self.gpu.is_some()
+    }
+
+    // The chunks are only gathered again when the scene changed.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &crate::terrain::Scene, camera: &crate::camera::Camera, projection: &crate::camera::Projection) {
+        if self.revision != Some(scene.revision()) {
+            self.revision = Some(scene.revision());
+            self.gather(device, queue, scene);
+        }
+
+        let frustum = projection.frustum(camera);
+        match &self.gpu {
+            Some(gpu) => {
+                let uniform = CullUniform {
+                    planes: frustum.planes.map(|plane| [plane.normal.x, plane.normal.y, plane.normal.z, plane.distance]),
+                    chunk_count: self.chunks.len() as u32,
+                    _padding: [0; 3]
+                };
+                queue.write_buffer(&gpu.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+            }
+            None => {
+                self.visible = self.chunks.iter()
+                    .map(|chunk| frustum.contains_aabb(chunk.min.into(), chunk.max.into()))
+                    .collect();
+            }
+        }
+    }
+
+    fn gather(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &crate::terrain::Scene) {
+        let mut chunks: Vec<_> = scene.chunks().collect();
+        chunks.sort_by_key(|(_, range)| range.page);
+
+        let size = crate::terrain::Scene::CHUNK_SIZE as f32;
+        self.chunks = chunks.iter().map(|(chunk_coord, range)| {
+            let min = chunk_coord.map(|v| v as f32 * size);
+            ChunkBounds {
+                min,
+                first_instance: range.offset,
+                max: min.map(|v| v + size),
+                instance_count: range.count
+            }
+        }).collect();
+
+        self.pages = (0..scene.arena().page_count()).map(|page| {
+            let start = chunks.partition_point(|(_, range)| range.page < page) as u32;
+            let end = chunks.partition_point(|(_, range)| range.page <= page) as u32;
+            start..end
+        }).collect();
+
+        let Some(gpu) = &mut self.gpu else { return; };
+        if self.chunks.is_empty() {
+            gpu.bind_group = None;
+            return;
+        }
+
+        gpu.bounds_buffer.update(device, queue, bytemuck::cast_slice(&self.chunks));
+        gpu.indirect_buffer.reserve(device, queue, self.chunks.len() as wgpu::BufferAddress * Self::DRAW_SIZE);
+        // The buffers may have been replaced.
+        gpu.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[cull] Bind Group"),
+            layout: &gpu.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: gpu.uniform_buffer.as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: gpu.bounds_buffer.buffer().as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: gpu.indirect_buffer.buffer().as_entire_binding()
+                }
+            ]
+        }));
+    }
+
+    // Records the compute pass, if any. Has to come before the render pass drawing the chunks.
+    pub fn cull(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(GpuCuller { pipeline, bind_group: Some(bind_group), .. }) = &self.gpu else { return; };
+
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("[cull] Compute Pass"),
+            timestamp_writes: None
+        });
+        cpass.set_pipeline(pipeline);
+        cpass.set_bind_group(0, bind_group, &[]);
+        cpass.dispatch_workgroups((self.chunks.len() as u32).div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+    }
+
+    // Draws the visible chunks of `scene` as in `terrain::Scene::draw`.
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, scene: &'a crate::terrain::Scene) {
+        for (page, range) in self.pages.iter().enumerate() {
+            if range.is_empty() { continue; }
+
+            rpass.set_vertex_buffer(0, scene.arena().buffer(page).slice(..));
+            match &self.gpu {
+                Some(gpu) if gpu.multi_draw => {
+                    rpass.multi_draw_indirect(gpu.indirect_buffer.buffer(), range.start as wgpu::BufferAddress * Self::DRAW_SIZE, range.len() as u32);
+                }
+                Some(gpu) => {
+                    for i in range.clone() {
+                        rpass.draw_indirect(gpu.indirect_buffer.buffer(), i as wgpu::BufferAddress * Self::DRAW_SIZE);
+                    }
+                }
+                None => {
+                    for i in range.clone().filter(|&i| self.visible[i as usize]) {
+                        let chunk = &self.chunks[i as usize];
+                        rpass.draw(0..6, chunk.first_instance..chunk.first_instance + chunk.instance_count);
+                    }
+                }
+            }
+        }
+    }
+}
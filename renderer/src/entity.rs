@@ -0,0 +1,725 @@
+use cgmath::InnerSpace;
+
+use crate::terrain::{IntCoord, WorldInterface, MAX_LIGHT};
+
+// A box in model space, measured in pixels (1/16 of a block). `uv` is the top-left corner of
+// its unwrapped faces in the skin, laid out the same way as Minecraft box UVs.
+#[derive(Clone, Copy)]
+pub struct Cuboid {
+    pub origin: [f32; 3],
+    pub size: [f32; 3],
+    pub uv: [u32; 2]
+}
+
+pub struct ModelPart {
+    pub name: String,
+    // Parents must come before their children in `EntityModel::parts`.
+    pub parent: Option<usize>,
+    pub pivot: [f32; 3],
+    // Rest pose as Euler angles in radians, applied in Z, Y, X order.
+    pub rotation: [f32; 3],
+    pub cuboids: Vec<Cuboid>
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Property {
+    Rotation,
+    Translation
+}
+
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+    pub time: f32,
+    pub value: [f32; 3]
+}
+
+pub struct Channel {
+    pub part: usize,
+    pub property: Property,
+    // Sorted by time.
+    pub keyframes: Vec<Keyframe>
+}
+
+impl Channel {
+    fn sample(&self, time: f32) -> cgmath::Vector3<f32> {
+        let (first, last) = match (self.keyframes.first(), self.keyframes.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return cgmath::Vector3::new(0.0, 0.0, 0.0)
+        };
+        if time <= first.time { return first.value.into(); }
+        if time >= last.time { return last.value.into(); }
+
+        let next = self.keyframes.iter().position(|key| key.time > time).unwrap();
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = (time - a.time) / (b.time - a.time);
+        cgmath::Vector3::from(a.value) * (1.0 - t) + cgmath::Vector3::from(b.value) * t
+    }
+}
+
+pub struct Animation {
+    pub name: String,
+    pub length: f32,
+    pub looping: bool,
+    pub channels: Vec<Channel>
+}
+
+pub struct EntityModel {
+    pub parts: Vec<ModelPart>,
+    pub animations: Vec<Animation>,
+    // Part turned by `Entity::head_yaw` and `Entity::head_pitch`.
+    pub head: Option<usize>
+}
+
+impl EntityModel {
+    pub fn part(&self, name: &str) -> Option<usize> {
+        self.parts.iter().position(|part| part.name == name)
+    }
+
+    pub fn animation(&self, name: &str) -> Option<usize> {
+        self.animations.iter().position(|animation| animation.name == name)
+    }
+
+    // A player-sized biped for 64x64 skins, with "walk" and "idle" animations.
+    pub fn humanoid() -> Self {
+        let part = |name: &str, parent, pivot, origin, size, uv| ModelPart {
+            name: name.into(),
+            parent,
+            pivot,
+            rotation: [0.0; 3],
+            cuboids: vec![Cuboid { origin, size, uv }]
+        };
+
+        let parts = vec![
+            part("body", None, [0.0, 24.0, 0.0], [-4.0, 12.0, -2.0], [8.0, 12.0, 4.0], [16, 16]),
+            part("head", Some(0), [0.0, 24.0, 0.0], [-4.0, 24.0, -4.0], [8.0, 8.0, 8.0], [0, 0]),
+            part("right_arm", Some(0), [-5.0, 22.0, 0.0], [-8.0, 12.0, -2.0], [4.0, 12.0, 4.0], [40, 16]),
+            part("left_arm", Some(0), [5.0, 22.0, 0.0], [4.0, 12.0, -2.0], [4.0, 12.0, 4.0], [32, 48]),
+            part("right_leg", Some(0), [-2.0, 12.0, 0.0], [-4.0, 0.0, -2.0], [4.0, 12.0, 4.0], [0, 16]),
+            part("left_leg", Some(0), [2.0, 12.0, 0.0], [0.0, 0.0, -2.0], [4.0, 12.0, 4.0], [16, 48])
+        ];
+
+        let swing = |part, length: f32, axis: usize, amplitude: f32| {
+            let mut value = [0.0; 3];
+            let keyframes = [(0.0, amplitude), (0.5, -amplitude), (1.0, amplitude)].map(|(t, v)| {
+                value[axis] = v;
+                Keyframe { time: t * length, value }
+            });
+            Channel { part, property: Property::Rotation, keyframes: keyframes.into() }
+        };
+
+        let animations = vec![
+            Animation {
+                name: "walk".into(),
+                length: 1.0,
+                looping: true,
+                channels: vec![
+                    swing(2, 1.0, 0, -0.6),
+                    swing(3, 1.0, 0, 0.6),
+                    swing(4, 1.0, 0, 0.6),
+                    swing(5, 1.0, 0, -0.6)
+                ]
+            },
+            Animation {
+                name: "idle".into(),
+                length: 3.0,
+                looping: true,
+                channels: vec![
+                    swing(2, 3.0, 2, 0.05),
+                    swing(3, 3.0, 2, -0.05)
+                ]
+            }
+        ];
+
+        Self { parts, animations, head: Some(1) }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ModelId(usize);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SkinId(u32);
+
+#[derive(Clone, Copy)]
+pub struct AnimationState {
+    // Index into `EntityModel::animations`.
+    pub animation: usize,
+    pub time: f32,
+    pub weight: f32
+}
+
+pub struct Entity {
+    pub model: ModelId,
+    pub skin: SkinId,
+    pub pos: cgmath::Point3<f32>,
+    // Body rotation around the y axis in radians. At 0 the model faces +z.
+    pub yaw: f32,
+    // Head rotation relative to the body, in radians. Positive pitch looks up.
+    pub head_yaw: f32,
+    pub head_pitch: f32,
+    pub scale: f32,
+    // Blended additively on top of the rest pose.
+    pub animations: Vec<AnimationState>
+}
+
+impl Entity {
+    pub const EYE_HEIGHT: f32 = 1.6;
+    const HEAD_YAW_LIMIT: f32 = 1.3;
+    const HEAD_PITCH_LIMIT: f32 = 1.2;
+
+    pub fn new(model: ModelId, skin: SkinId, pos: cgmath::Point3<f32>) -> Self {
+        Self {
+            model,
+            skin,
+            pos,
+            yaw: 0.0,
+            head_yaw: 0.0,
+            head_pitch: 0.0,
+            scale: 1.0,
+            animations: vec![]
+        }
+    }
+
+    pub fn play(&mut self, animation: usize, weight: f32) {
+        match self.animations.iter_mut().find(|state| state.animation == animation) {
+            Some(state) => state.weight = weight,
+            None => self.animations.push(AnimationState { animation, time: 0.0, weight })
+        }
+    }
+
+    pub fn stop(&mut self, animation: usize) {
+        self.animations.retain(|state| state.animation != animation);
+    }
+
+    pub fn tick(&mut self, secs: f32) {
+        for state in &mut self.animations {
+            state.time += secs;
+        }
+    }
+
+    // Turns the head towards `target`, within what a neck allows.
+    pub fn look_at(&mut self, target: cgmath::Point3<f32>) {
+        let eye = self.pos + cgmath::Vector3::unit_y() * Self::EYE_HEIGHT * self.scale;
+        let offset = target - eye;
+
+        let yaw = offset.x.atan2(offset.z) - self.yaw;
+        let yaw = (yaw + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU) - std::f32::consts::PI;
+        let pitch = offset.y.atan2((offset.x * offset.x + offset.z * offset.z).sqrt());
+
+        self.head_yaw = yaw.clamp(-Self::HEAD_YAW_LIMIT, Self::HEAD_YAW_LIMIT);
+        self.head_pitch = pitch.clamp(-Self::HEAD_PITCH_LIMIT, Self::HEAD_PITCH_LIMIT);
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct EntityVertex {
+    pos: [f32; 3],
+    tex_coords: [f32; 2],
+    normal: [f32; 3],
+    part: u32
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct EntityInstance {
+    bone_row: u32,
+    skin: u32,
+    sky_light: f32
+}
+
+struct ModelMesh {
+    model: EntityModel,
+    vertex_buffer: wgpu::Buffer,
+    vertex_count: u32
+}
+
+impl ModelMesh {
+    fn build(model: &EntityModel) -> Vec<EntityVertex> {
+        let size = EntityRenderer::SKIN_SIZE as f32;
+        let mut vertices = vec![];
+
+        for (part, model_part) in model.parts.iter().enumerate() {
+            for cuboid in &model_part.cuboids {
+                let [x0, y0, z0] = cuboid.origin;
+                let [w, h, d] = cuboid.size;
+                let (x1, y1, z1) = (x0 + w, y0 + h, z0 + d);
+                let [u, v] = cuboid.uv.map(|v| v as f32);
+
+                // (top-left corner, right, down, uv rect) of each face as seen from outside.
+                let faces = [
+                    ([x0, y1, z0], [0.0, 0.0, d], [0.0, -h, 0.0], [u, v + d, d, h]),
+                    ([x1, y1, z1], [0.0, 0.0, -d], [0.0, -h, 0.0], [u + d + w, v + d, d, h]),
+                    ([x0, y0, z1], [w, 0.0, 0.0], [0.0, 0.0, -d], [u + d + w, v, w, d]),
+                    ([x0, y1, z0], [w, 0.0, 0.0], [0.0, 0.0, d], [u + d, v, w, d]),
+                    ([x1, y1, z0], [-w, 0.0, 0.0], [0.0, -h, 0.0], [u + 2.0 * d + w, v + d, w, h]),
+                    ([x0, y1, z1], [w, 0.0, 0.0], [0.0, -h, 0.0], [u + d, v + d, w, h])
+                ];
+
+                for (origin, right, down, [u, v, uw, vh]) in faces {
+                    let origin = cgmath::Vector3::from(origin);
+                    let (right, down) = (cgmath::Vector3::from(right), cgmath::Vector3::from(down));
+                    let normal = down.cross(right).normalize();
+
+                    for (cx, cy) in [(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (0.0, 0.0), (1.0, 1.0), (1.0, 0.0)] {
+                        vertices.push(EntityVertex {
+                            pos: (origin + right * cx + down * cy).into(),
+                            tex_coords: [(u + uw * cx) / size, (v + vh * cy) / size],
+                            normal: normal.into(),
+                            part: part as u32
+                        });
+                    }
+                }
+            }
+        }
+
+        vertices
+    }
+
+    fn part_matrices(&self, entity: &Entity) -> Vec<cgmath::Matrix4<f32>> {
+        let parts = &self.model.parts;
+        let mut rotations: Vec<cgmath::Vector3<f32>> = parts.iter().map(|part| part.rotation.into()).collect();
+        let mut translations = vec![cgmath::Vector3::new(0.0, 0.0, 0.0); parts.len()];
+
+        for state in &entity.animations {
+            let Some(animation) = self.model.animations.get(state.animation) else { continue; };
+            let time = if animation.looping && animation.length > 0.0 {
+                state.time.rem_euclid(animation.length)
+            } else {
+                state.time.min(animation.length)
+            };
+
+            for channel in &animation.channels {
+                let value = channel.sample(time) * state.weight;
+                match channel.property {
+                    Property::Rotation => rotations[channel.part] += value,
+                    Property::Translation => translations[channel.part] += value
+                }
+            }
+        }
+
+        if let Some(head) = self.model.head {
+            rotations[head].x -= entity.head_pitch;
+            rotations[head].y += entity.head_yaw;
+        }
+
+        let root = cgmath::Matrix4::from_translation(entity.pos - cgmath::Point3::new(0.0, 0.0, 0.0))
+            * cgmath::Matrix4::from_angle_y(cgmath::Rad(entity.yaw))
+            * cgmath::Matrix4::from_scale(entity.scale / 16.0);
+
+        let mut matrices: Vec<cgmath::Matrix4<f32>> = Vec::with_capacity(parts.len());
+        for (i, part) in parts.iter().enumerate() {
+            let pivot = cgmath::Vector3::from(part.pivot);
+            let rotation = rotations[i];
+            let local = cgmath::Matrix4::from_translation(pivot + translations[i])
+                * cgmath::Matrix4::from_angle_z(cgmath::Rad(rotation.z))
+                * cgmath::Matrix4::from_angle_y(cgmath::Rad(rotation.y))
+                * cgmath::Matrix4::from_angle_x(cgmath::Rad(rotation.x))
+                * cgmath::Matrix4::from_translation(-pivot);
+
+            let parent = part.parent.map_or(root, |parent| matrices[parent]);
+            matrices.push(parent * local);
+        }
+
+        matrices
+    }
+}
+
+pub struct EntityRenderer {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    sample_count: u32,
+    reversed_z: bool,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    skin_texture: wgpu::Texture,
+    skin_view: wgpu::TextureView,
+    skin_count: u32,
+    bone_texture: wgpu::Texture,
+    bone_capacity: u32,
+    bind_group: wgpu::BindGroup,
+    models: Vec<ModelMesh>,
+    // Instances are grouped by model, each batch is drawn with a single call.
+    batches: Vec<(usize, std::ops::Range<u32>)>,
+    instance_buffer: crate::utils::DynamicBuffer
+}
+
+impl EntityRenderer {
+    pub const SKIN_SIZE: u32 = 64;
+    pub const MAX_SKINS: u32 = 16;
+    pub const MAX_PARTS: usize = 32;
+
+    // The camera and environment bind groups are set by the caller at indices 1 and 2,
+    // the same as for `terrain::TerrainRenderer`.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment_layout: &wgpu::BindGroupLayout
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[entity] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/environment.wgsl"),
+                include_str!("shaders/entity.wgsl")
+            ).into())
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[entity] Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[entity] Render Pipeline Layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                camera_layout,
+                environment_layout
+            ],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, format, depth_format, sample_count, false);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("[entity] Skin Sampler"),
+            ..Default::default()
+        });
+
+        let skin_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[entity] Skin Texture"),
+            size: wgpu::Extent3d {
+                width: Self::SKIN_SIZE,
+                height: Self::SKIN_SIZE,
+                depth_or_array_layers: Self::MAX_SKINS
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+
+        let skin_view = skin_texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let bone_capacity = 16;
+        let bone_texture = Self::create_bone_texture(device, bone_capacity);
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &bone_texture, &skin_view, &sampler);
+
+        Self {
+            pipeline,
+            pipeline_layout,
+            shader,
+            format,
+            depth_format,
+            sample_count,
+            reversed_z: false,
+            bind_group_layout,
+            sampler,
+            skin_texture,
+            skin_view,
+            skin_count: 0,
+            bone_texture,
+            bone_capacity,
+            bind_group,
+            models: vec![],
+            batches: vec![],
+            instance_buffer: crate::utils::DynamicBuffer::new(
+                device,
+                "[entity] Instance Buffer".into(),
+                256,
+                wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
+            )
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        reversed_z: bool
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[entity] Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<EntityVertex>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 3 => Uint32]
+                    },
+                    wgpu::VertexBufferLayout {
+                        array_stride: std::mem::size_of::<EntityInstance>() as wgpu::BufferAddress,
+                        step_mode: wgpu::VertexStepMode::Instance,
+                        attributes: &wgpu::vertex_attr_array![4 => Uint32, 5 => Uint32, 6 => Float32]
+                    }
+                ]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: if reversed_z { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less },
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None
+        })
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, self.depth_format, sample_count, self.reversed_z);
+    }
+
+    // Has to match `camera::Projection::reversed_z` of the rendered camera.
+    pub fn set_reversed_z(&mut self, device: &wgpu::Device, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, self.depth_format, self.sample_count, reversed_z);
+    }
+
+    // One row per entity, holding the four columns of every part matrix.
+    fn create_bone_texture(device: &wgpu::Device, capacity: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[entity] Bone Texture"),
+            size: wgpu::Extent3d {
+                width: Self::MAX_PARTS as u32 * 4,
+                height: capacity,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        bone_texture: &wgpu::Texture,
+        skin_view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[entity] Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&bone_texture.create_view(&Default::default()))
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(skin_view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler)
+                }
+            ]
+        })
+    }
+
+    // None if the model has more than `MAX_PARTS` parts, a part comes before its parent, or an
+    // animation channel or the head refers to a part the model doesn't have.
+    pub fn add_model(&mut self, device: &wgpu::Device, model: EntityModel) -> Option<ModelId> {
+        use wgpu::util::DeviceExt;
+
+        let part_count = model.parts.len();
+        if part_count > Self::MAX_PARTS
+            || !model.parts.iter().enumerate().all(|(i, part)| part.parent.map_or(true, |parent| parent < i))
+            || !model.animations.iter().flat_map(|animation| &animation.channels).all(|channel| channel.part < part_count)
+            || !model.head.map_or(true, |head| head < part_count)
+        {
+            return None;
+        }
+
+        let vertices = ModelMesh::build(&model);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("[entity] Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX
+        });
+
+        self.models.push(ModelMesh {
+            model,
+            vertex_buffer,
+            vertex_count: vertices.len() as u32
+        });
+        Some(ModelId(self.models.len() - 1))
+    }
+
+    pub fn model(&self, id: ModelId) -> &EntityModel {
+        &self.models[id.0].model
+    }
+
+    // `data` is a SKIN_SIZE x SKIN_SIZE RGBA8 image. None once `MAX_SKINS` skins were added.
+    pub fn add_skin(&mut self, queue: &wgpu::Queue, data: &[u8]) -> Option<SkinId> {
+        if self.skin_count >= Self::MAX_SKINS {
+            return None;
+        }
+        assert_eq!(data.len(), (Self::SKIN_SIZE * Self::SKIN_SIZE * 4) as usize);
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.skin_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: 0, y: 0, z: self.skin_count },
+                aspect: wgpu::TextureAspect::All
+            },
+            data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(Self::SKIN_SIZE * 4),
+                rows_per_image: Some(Self::SKIN_SIZE)
+            },
+            wgpu::Extent3d {
+                width: Self::SKIN_SIZE,
+                height: Self::SKIN_SIZE,
+                depth_or_array_layers: 1
+            }
+        );
+
+        self.skin_count += 1;
+        Some(SkinId(self.skin_count - 1))
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &impl WorldInterface, entities: &[Entity]) {
+        self.batches.clear();
+        if entities.is_empty() { return; }
+
+        if entities.len() as u32 > self.bone_capacity {
+            self.bone_capacity = (entities.len() as u32).next_power_of_two();
+            self.bone_texture = Self::create_bone_texture(device, self.bone_capacity);
+            self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &self.bone_texture, &self.skin_view, &self.sampler);
+        }
+
+        let mut order: Vec<usize> = (0..entities.len()).collect();
+        order.sort_by_key(|&i| entities[i].model.0);
+
+        let row_len = Self::MAX_PARTS * 4;
+        let mut bones = vec![[0.0f32; 4]; row_len * entities.len()];
+        let mut instances = Vec::with_capacity(entities.len());
+
+        for (row, &i) in order.iter().enumerate() {
+            let entity = &entities[i];
+            let mesh = &self.models[entity.model.0];
+
+            for (part, matrix) in mesh.part_matrices(entity).iter().enumerate() {
+                let columns: [[f32; 4]; 4] = (*matrix).into();
+                bones[row * row_len + part * 4..][..4].copy_from_slice(&columns);
+            }
+
+            let coord = IntCoord([entity.pos.x.floor() as i32, (entity.pos.y + 0.5).floor() as i32, entity.pos.z.floor() as i32]);
+            let sky_light = world.get_sky_light(&coord).unwrap_or(MAX_LIGHT);
+            instances.push(EntityInstance {
+                bone_row: row as u32,
+                skin: entity.skin.0,
+                sky_light: sky_light as f32 / MAX_LIGHT as f32
+            });
+
+            match self.batches.last_mut() {
+                Some((model, range)) if *model == entity.model.0 => range.end += 1,
+                _ => self.batches.push((entity.model.0, row as u32..row as u32 + 1))
+            }
+        }
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.bone_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All
+            },
+            bytemuck::cast_slice(&bones),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(row_len as u32 * 16),
+                rows_per_image: None
+            },
+            wgpu::Extent3d {
+                width: row_len as u32,
+                height: entities.len() as u32,
+                depth_or_array_layers: 1
+            }
+        );
+
+        self.instance_buffer.update(device, queue, bytemuck::cast_slice(&instances));
+    }
+
+    pub fn render<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        if self.batches.is_empty() { return; }
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(..));
+
+        for (model, instances) in &self.batches {
+            let mesh = &self.models[*model];
+            rpass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            rpass.draw(0..mesh.vertex_count, instances.clone());
+        }
+    }
+}
@@ -0,0 +1,200 @@
+pub struct WorldClock {
+    // In [0, 1): 0 is sunrise, 0.25 noon, 0.5 sunset and 0.75 midnight.
+    pub time_of_day: f32,
+    pub day_length: f32,
+    pub speed: f32,
+    pub paused: bool
+}
+
+impl WorldClock {
+    pub const DEFAULT_DAY_LENGTH: f32 = 1200.0;
+
+    pub fn new(time_of_day: f32) -> Self {
+        Self {
+            time_of_day,
+            day_length: Self::DEFAULT_DAY_LENGTH,
+            speed: 1.0,
+            paused: false
+        }
+    }
+
+    pub fn tick(&mut self, secs: f32) {
+        if self.paused { return; }
+        self.time_of_day = (self.time_of_day + secs * self.speed / self.day_length).rem_euclid(1.0);
+    }
+
+    pub fn sun_direction(&self) -> cgmath::Vector3<f32> {
+        let angle = self.time_of_day * std::f32::consts::TAU;
+        cgmath::Vector3::new(angle.cos(), angle.sin(), 0.0)
+    }
+
+    pub fn daylight(&self) -> f32 {
+        (self.sun_direction().y * 4.0 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FogMode {
+    Linear,
+    Exponential
+}
+
+pub struct FogSettings {
+    pub mode: FogMode,
+    pub render_distance: f32,
+    // Where linear fog starts, as a fraction of the render distance.
+    pub start: f32,
+    pub underwater_color: [f32; 3],
+    pub underwater_density: f32
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            mode: FogMode::Linear,
+            render_distance: 96.0,
+            start: 0.7,
+            underwater_color: [0.02, 0.08, 0.2],
+            underwater_density: 0.12
+        }
+    }
+}
+
+pub struct ShadingSettings {
+    pub directional: bool,
+    // Brightness of each face, indexed by `terrain::Direction`.
+    pub face_factors: [f32; 6],
+    // How much faces turned away from the sun are darkened, from 0.0 (off) to 1.0.
+    pub sun_lambert: f32
+}
+
+impl Default for ShadingSettings {
+    fn default() -> Self {
+        Self {
+            directional: true,
+            face_factors: [0.6, 0.6, 0.5, 1.0, 0.8, 0.8],
+            sun_lambert: 0.0
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct EnvironmentUniform {
+    sun_dir: [f32; 4],
+    zenith_color: [f32; 4],
+    horizon_color: [f32; 4],
+    sunset_color: [f32; 4],
+    light: [f32; 4],
+    fog_color: [f32; 4],
+    fog: [f32; 4],
+    face_factors: [[f32; 4]; 2],
+    shading: [f32; 4]
+}
+
+impl EnvironmentUniform {
+    const DAY_ZENITH: [f32; 3] = [0.18, 0.36, 0.85];
+    const DAY_HORIZON: [f32; 3] = [0.60, 0.75, 0.95];
+    const NIGHT_ZENITH: [f32; 3] = [0.002, 0.003, 0.01];
+    const NIGHT_HORIZON: [f32; 3] = [0.01, 0.015, 0.03];
+    const SUNSET: [f32; 3] = [0.9, 0.4, 0.15];
+    const MIN_BRIGHTNESS: f32 = 0.15;
+    const FOG_LINEAR: f32 = 0.0;
+    const FOG_EXPONENTIAL: f32 = 1.0;
+    const FOG_UNDERWATER: f32 = 2.0;
+
+    pub fn new(clock: &WorldClock, fog: &FogSettings, underwater: bool, shading: &ShadingSettings) -> Self {
+        let sun_dir = clock.sun_direction();
+        let daylight = clock.daylight();
+        let sunset = (1.0 - sun_dir.y.abs() * 4.0).clamp(0.0, 1.0);
+        let brightness = Self::MIN_BRIGHTNESS + (1.0 - Self::MIN_BRIGHTNESS) * daylight;
+        let horizon_color = mix(Self::NIGHT_HORIZON, Self::DAY_HORIZON, daylight);
+
+        let end = fog.render_distance;
+        let (fog_color, fog_params) = if underwater {
+            let [r, g, b] = fog.underwater_color;
+            ([r * brightness, g * brightness, b * brightness, 1.0], [0.0, end, fog.underwater_density, Self::FOG_UNDERWATER])
+        } else {
+            let mode = match fog.mode {
+                FogMode::Linear => Self::FOG_LINEAR,
+                FogMode::Exponential => Self::FOG_EXPONENTIAL
+            };
+            // Exponential-squared fog reaches 98% at the render distance.
+            (horizon_color, [end * fog.start, end, 2.0 / end, mode])
+        };
+
+        let f = if shading.directional { shading.face_factors } else { [1.0; 6] };
+
+        Self {
+            sun_dir: [sun_dir.x, sun_dir.y, sun_dir.z, daylight],
+            zenith_color: mix(Self::NIGHT_ZENITH, Self::DAY_ZENITH, daylight),
+            horizon_color,
+            sunset_color: [Self::SUNSET[0], Self::SUNSET[1], Self::SUNSET[2], sunset],
+            light: [brightness, Self::MIN_BRIGHTNESS, clock.time_of_day, 0.0],
+            fog_color,
+            fog: fog_params,
+            face_factors: [[f[0], f[1], f[2], f[3]], [f[4], f[5], 1.0, 1.0]],
+            shading: [shading.sun_lambert, 0.0, 0.0, 0.0]
+        }
+    }
+}
+
+fn mix(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 4] {
+    [
+        a[0] + (b[0] - a[0]) * t,
+        a[1] + (b[1] - a[1]) * t,
+        a[2] + (b[2] - a[2]) * t,
+        1.0
+    ]
+}
+
+pub struct Environment {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+impl Environment {
+    pub fn new(device: &wgpu::Device, layout: &wgpu::BindGroupLayout) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[environment] Uniform Buffer"),
+            size: std::mem::size_of::<EnvironmentUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[environment] Uniform Bind Group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding()
+            }]
+        });
+
+        Self { buffer, bind_group }
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, clock: &WorldClock, fog: &FogSettings, underwater: bool, shading: &ShadingSettings) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[EnvironmentUniform::new(clock, fog, underwater, shading)]));
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[environment] Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        })
+    }
+}
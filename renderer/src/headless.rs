@@ -0,0 +1,376 @@
+// Format of the images rendered by `HeadlessRenderer`.
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// Tightly packed RGBA8 pixels, row by row from the top.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>
+}
+
+// A copy of a 4 bytes per pixel texture with `COPY_SRC` usage back to the CPU, finished
+// asynchronously. The mapping is only completed by `wgpu::Device::poll`, so without waiting it
+// has to be polled every frame.
+pub struct Readback {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+    receiver: std::sync::mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>
+}
+
+impl Readback {
+    // Submits the copy, so everything drawn to `texture` has to be submitted before.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Self {
+        let (width, height) = (texture.width(), texture.height());
+
+        // Rows of the copy have to start at multiples of the alignment.
+        let padded_bytes_per_row = (width * 4).div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[headless] Readback Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("[headless] Readback Encoder")
+        });
+
+        encoder.copy_texture_to_buffer(texture.as_image_copy(), wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height)
+            }
+        }, texture.size());
+
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        let bgra = matches!(texture.format(), wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb);
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            bgra,
+            receiver
+        }
+    }
+
+    // `None` while the copy isn't done yet.
+    pub fn try_finish(&self) -> Option<Result<Image, wgpu::BufferAsyncError>> {
+        let result = self.receiver.try_recv().ok()?;
+        Some(result.map(|_| self.image()))
+    }
+
+    fn image(&self) -> Image {
+        let bytes_per_row = (self.width * 4) as usize;
+        let mut data: Vec<u8> = self.buffer.slice(..).get_mapped_range()
+            .chunks(self.padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..bytes_per_row])
+            .copied()
+            .collect();
+        self.buffer.unmap();
+
+        if self.bgra {
+            data.chunks_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+
+        Image {
+            width: self.width,
+            height: self.height,
+            data
+        }
+    }
+}
+
+// Like `Readback`, but blocks until the GPU is done, which isn't possible on the web.
+pub fn read_texture(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> Result<Image, wgpu::BufferAsyncError> {
+    let readback = Readback::new(device, queue, texture);
+    device.poll(wgpu::Maintain::Wait);
+    readback.receiver.recv().unwrap()?;
+    Ok(readback.image())
+}
+
+pub struct HeadlessSettings {
+    pub projection: crate::camera::Projection,
+    pub clock: crate::environment::WorldClock,
+    pub fog: crate::environment::FogSettings,
+    pub shading: crate::environment::ShadingSettings,
+    pub shadow: crate::shadow::ShadowSettings,
+    pub cloud: crate::cloud::CloudSettings,
+    pub water: crate::water::WaterSettings,
+    pub post: crate::post::PostSettings
+}
+
+impl Default for HeadlessSettings {
+    fn default() -> Self {
+        Self {
+            projection: Default::default(),
+            clock: crate::environment::WorldClock::new(0.15),
+            fog: Default::default(),
+            shading: Default::default(),
+            shadow: Default::default(),
+            cloud: Default::default(),
+            water: Default::default(),
+            post: Default::default()
+        }
+    }
+}
+
+struct Targets {
+    color: wgpu::Texture,
+    color_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView
+}
+
+// Renders terrain, water, sky and clouds without a window, for thumbnails, server-side renders
+// and tests. Everything time dependent is drawn as it is at time zero.
+pub struct HeadlessRenderer {
+    scene: crate::terrain::Scene,
+    texture: crate::terrain::TerrainTexture,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    environment: crate::environment::Environment,
+    shadow_renderer: crate::shadow::ShadowRenderer,
+    terrain_renderer: crate::terrain::TerrainRenderer,
+    sky_renderer: crate::sky::SkyRenderer,
+    water_renderer: crate::water::WaterRenderer,
+    cloud_renderer: crate::cloud::CloudRenderer,
+    post_processor: crate::post::PostProcessor,
+    reversed_z: bool,
+    targets: Option<Targets>
+}
+
+impl HeadlessRenderer {
+    // `atlas` is the RGBA8 block texture, as given to `terrain::Scene::create_texture`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmap_generator: &mut crate::mipmap::MipmapGenerator,
+        atlas_width: u32,
+        atlas_height: u32,
+        atlas: &[u8]
+    ) -> Self {
+        let hdr_format = crate::post::HDR_FORMAT;
+
+        let texture_layout = crate::terrain::Scene::texture_bind_group_layout(device);
+        let texture = crate::terrain::Scene::create_texture(device, queue, mipmap_generator, &texture_layout, atlas_width, atlas_height, atlas, &Default::default());
+
+        let camera_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[headless] Camera Buffer"),
+            size: std::mem::size_of::<crate::camera::CameraUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let camera_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[headless] Camera Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        });
+
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[headless] Camera Bind Group"),
+            layout: &camera_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding()
+            }]
+        });
+
+        let environment_layout = crate::environment::Environment::bind_group_layout(device);
+        let environment = crate::environment::Environment::new(device, &environment_layout);
+
+        let shadow_renderer = crate::shadow::ShadowRenderer::new(device, &Default::default());
+
+        let terrain_renderer = crate::terrain::TerrainRenderer::new(
+            device,
+            hdr_format,
+            DEPTH_FORMAT,
+            1,
+            &texture_layout,
+            &camera_layout,
+            &environment_layout,
+            shadow_renderer.bind_group_layout()
+        );
+
+        let sky_renderer = crate::sky::SkyRenderer::new(device, hdr_format, 1, &environment_layout);
+
+        let water_renderer = crate::water::WaterRenderer::new(device, hdr_format, DEPTH_FORMAT, 1, &environment_layout);
+
+        let cloud_renderer = crate::cloud::CloudRenderer::new(device, hdr_format, DEPTH_FORMAT, 1, &camera_layout, &environment_layout);
+
+        let post_processor = crate::post::PostProcessor::new(device, queue, FORMAT, 1, 1);
+
+        Self {
+            scene: crate::terrain::Scene::new(device),
+            texture,
+            camera_buffer,
+            camera_bind_group,
+            environment,
+            shadow_renderer,
+            terrain_renderer,
+            sky_renderer,
+            water_renderer,
+            cloud_renderer,
+            post_processor,
+            reversed_z: false,
+            targets: None
+        }
+    }
+
+    fn create_targets(&mut self, device: &wgpu::Device, width: u32, height: u32) -> Targets {
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1
+        };
+
+        let color = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[headless] Color Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[]
+        });
+
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[headless] Depth Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[]
+        });
+
+        let depth_view = depth.create_view(&Default::default());
+
+        self.post_processor.resize(device, width, height);
+        self.water_renderer.set_targets(device, &depth_view, width, height);
+
+        Targets {
+            color_view: color.create_view(&Default::default()),
+            color,
+            depth_view
+        }
+    }
+
+    // The scene is kept between calls and updated from `world` like `terrain::Scene::update`,
+    // so on the first call every block has to be reported as updated. The aspect of `camera`
+    // should match the size.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        world: &impl crate::terrain::WorldInterface,
+        camera: &crate::camera::Camera,
+        width: u32,
+        height: u32,
+        settings: &HeadlessSettings
+    ) -> Result<Image, wgpu::BufferAsyncError> {
+        let resized = match &self.targets {
+            Some(targets) => targets.color.width() != width || targets.color.height() != height,
+            None => true
+        };
+        if resized {
+            self.targets = Some(self.create_targets(device, width, height));
+        }
+
+        let projection = &settings.projection;
+        if projection.reversed_z != self.reversed_z {
+            self.reversed_z = projection.reversed_z;
+            self.terrain_renderer.set_reversed_z(device, self.reversed_z);
+            self.water_renderer.set_reversed_z(device, self.reversed_z);
+            self.cloud_renderer.set_reversed_z(device, self.reversed_z);
+        }
+
+        self.scene.update(device, queue, world);
+
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[crate::camera::CameraUniform::with_projection(camera, projection)]));
+        self.environment.update(queue, &settings.clock, &settings.fog, false, &settings.shading);
+        self.sky_renderer.update(queue, camera, projection);
+        self.terrain_renderer.update(device, queue, &self.scene, camera, projection);
+        self.shadow_renderer.update(device, queue, camera, projection, settings.clock.sun_direction(), &settings.shadow);
+        self.cloud_renderer.update(device, queue, camera, 0.0, &settings.cloud);
+        self.water_renderer.update(queue, camera, projection, 0.0, &settings.water);
+        self.post_processor.update(queue, &settings.post);
+
+        let targets = self.targets.as_ref().unwrap();
+        let hdr_view = self.post_processor.hdr_view();
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("[headless] Render Encoder")
+        });
+
+        self.shadow_renderer.render(&mut encoder, &self.scene);
+        self.terrain_renderer.cull(&mut encoder);
+
+        self.sky_renderer.render(&mut encoder, hdr_view, &self.environment);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("[headless] Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: hdr_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store
+                }
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &targets.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(projection.depth_clear_value()),
+                    store: wgpu::StoreOp::Store
+                }),
+                stencil_ops: None
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None
+        });
+
+        render_pass.set_bind_group(0, self.texture.bind_group(), &[]);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_bind_group(2, self.environment.bind_group(), &[]);
+        render_pass.set_bind_group(3, self.shadow_renderer.bind_group(), &[]);
+        self.terrain_renderer.render(&mut render_pass, &self.scene);
+
+        drop(render_pass);
+
+        self.water_renderer.render(&mut encoder, self.post_processor.hdr_texture(), hdr_view, &targets.depth_view, &self.environment, &self.scene);
+
+        self.cloud_renderer.render(&mut encoder, hdr_view, &targets.depth_view, &self.camera_bind_group, &self.environment);
+
+        self.post_processor.render(&mut encoder, &targets.color_view);
+
+        queue.submit(Some(encoder.finish()));
+
+        read_texture(device, queue, &targets.color)
+    }
+}
@@ -1,4 +1,20 @@
 mod utils;
+// So the fixtures in `tests/common` name the crate the same from unit tests.
+#[cfg(test)]
+extern crate self as renderer;
+pub mod arena;
+pub mod cull;
+pub mod meshing;
+pub mod mipmap;
 pub mod camera;
 pub mod terrain;
 pub mod indicator;
+pub mod sky;
+pub mod environment;
+pub mod shadow;
+pub mod cloud;
+pub mod particle;
+pub mod entity;
+pub mod water;
+pub mod post;
+pub mod headless;
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use crate::terrain::{Face, IntCoord, Scene, WorldInterface, MAX_LIGHT};
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct MeshingParams {
+    chunk_count: u32,
+    capacity: u32,
+    _padding: [u32; 2]
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ChunkInfo {
+    origin: [i32; 3],
+    first_block: u32
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct BlockInfo {
+    model: u32,
+    light: u32
+}
+
+// Finds the visible faces of whole chunks in a compute pass, for `Scene::set_gpu_meshing`.
+// Block models are given ids by their faces, and the chunks uploaded as ids with a border of
+// one block. Faces are appended with an atomic counter and read back, as the scene keeps them
+// for later updates. Needs compute shaders, so it isn't available on WebGL.
+pub struct GpuMesher {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    count_buffer: wgpu::Buffer,
+    chunk_buffer: crate::utils::DynamicBuffer,
+    block_buffer: crate::utils::DynamicBuffer,
+    model_buffer: crate::utils::DynamicBuffer,
+    face_buffer: crate::utils::DynamicBuffer
+}
+
+impl GpuMesher {
+    const WORKGROUP_SIZE: u32 = 64;
+    const PADDED_SIZE: i32 = Scene::CHUNK_SIZE + 2;
+    // Keeps the dispatch within the limits and the buffers small.
+    const MAX_BATCH_CHUNKS: usize = 64;
+
+    pub fn new(device: &wgpu::Device) -> Option<Self> {
+        let limits = device.limits();
+        if limits.max_compute_invocations_per_workgroup < Self::WORKGROUP_SIZE || limits.max_storage_buffers_per_shader_stage < 5 {
+            return None;
+        }
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/meshing.wgsl"));
+
+        let buffer_entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+        let read_only = wgpu::BufferBindingType::Storage { read_only: true };
+        let read_write = wgpu::BufferBindingType::Storage { read_only: false };
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[meshing] Bind Group Layout"),
+            entries: &[
+                buffer_entry(0, wgpu::BufferBindingType::Uniform),
+                buffer_entry(1, read_only),
+                buffer_entry(2, read_only),
+                buffer_entry(3, read_only),
+                buffer_entry(4, read_write),
+                buffer_entry(5, read_write)
+            ]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[meshing] Compute Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("[meshing] Compute Pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main"
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[meshing] Params Buffer"),
+            size: std::mem::size_of::<MeshingParams>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let count_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[meshing] Face Count Buffer"),
+            size: 4,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let storage_buffer = |label: &str, initial_size| crate::utils::DynamicBuffer::new(
+            device,
+            format!("[meshing] {label}"),
+            initial_size,
+            wgpu::BufferUsages::STORAGE
+        );
+
+        Some(Self {
+            pipeline,
+            bind_group_layout,
+            params_buffer,
+            count_buffer,
+            chunk_buffer: storage_buffer("Chunk Buffer", 1024),
+            block_buffer: storage_buffer("Block Buffer", 1 << 16),
+            model_buffer: storage_buffer("Model Buffer", 1024),
+            face_buffer: storage_buffer("Face Buffer", 1 << 16)
+        })
+    }
+
+    // All faces of the chunks at `chunk_coords`, in no particular order. Waits for the GPU.
+    pub(crate) fn mesh(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &impl WorldInterface, chunk_coords: &[[i32; 3]]) -> Vec<Face> {
+        let mut faces = vec![];
+        for batch in chunk_coords.chunks(Self::MAX_BATCH_CHUNKS) {
+            faces.extend(self.mesh_batch(device, queue, world, batch));
+        }
+        faces
+    }
+
+    fn mesh_batch(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &impl WorldInterface, chunk_coords: &[[i32; 3]]) -> Vec<Face> {
+        // Model 0 has no faces, for blocks without any.
+        let mut models = vec![[0u32; 6]];
+        let mut model_ids = HashMap::from([([0u32; 6], 0u32)]);
+        let mut chunks = Vec::with_capacity(chunk_coords.len());
+        let mut blocks = Vec::with_capacity(chunk_coords.len() * Self::PADDED_SIZE.pow(3) as usize);
+        // Faces that a block has, an upper bound for those that are visible.
+        let mut capacity = 0;
+
+        for chunk_coord in chunk_coords {
+            let origin = chunk_coord.map(|v| v * Scene::CHUNK_SIZE);
+            chunks.push(ChunkInfo {
+                origin,
+                first_block: blocks.len() as u32
+            });
+
+            for z in -1..Scene::CHUNK_SIZE + 1 {
+                for y in -1..Scene::CHUNK_SIZE + 1 {
+                    for x in -1..Scene::CHUNK_SIZE + 1 {
+                        let coord = IntCoord([origin[0] + x, origin[1] + y, origin[2] + z]);
+                        let faces = world.get_block(&coord).faces.map(|face| face.map_or(0, |texture| texture.get().into()));
+                        let model = *model_ids.entry(faces).or_insert_with(|| {
+                            models.push(faces);
+                            models.len() as u32 - 1
+                        });
+
+                        let inside = [x, y, z].iter().all(|v| (0..Scene::CHUNK_SIZE).contains(v));
+                        if inside {
+                            capacity += faces.iter().filter(|&&texture| texture != 0).count() as u32;
+                        }
+
+                        let sky_light = world.get_sky_light(&coord).unwrap_or(MAX_LIGHT) as u32;
+                        let emission = world.get_emission(&coord) as u32;
+                        blocks.push(BlockInfo {
+                            model,
+                            light: sky_light | emission << 8
+                        });
+                    }
+                }
+            }
+        }
+
+        if capacity == 0 {
+            return vec![];
+        }
+
+        let face_size = std::mem::size_of::<Face>() as wgpu::BufferAddress;
+        let faces_size = capacity as wgpu::BufferAddress * face_size;
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::cast_slice(&[MeshingParams {
+            chunk_count: chunks.len() as u32,
+            capacity,
+            _padding: [0; 2]
+        }]));
+        queue.write_buffer(&self.count_buffer, 0, bytemuck::cast_slice(&[0u32]));
+        self.chunk_buffer.update(device, queue, bytemuck::cast_slice(&chunks));
+        self.block_buffer.update(device, queue, bytemuck::cast_slice(&blocks));
+        self.model_buffer.update(device, queue, bytemuck::cast_slice(&models));
+        self.face_buffer.reserve(device, queue, faces_size);
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[meshing] Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params_buffer.as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.chunk_buffer.buffer().as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.block_buffer.buffer().as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: self.model_buffer.buffer().as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: self.count_buffer.as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: self.face_buffer.buffer().as_entire_binding()
+                }
+            ]
+        });
+
+        // The count first, then the faces.
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[meshing] Readback Buffer"),
+            size: 4 + faces_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("[meshing] Compute Encoder")
+        });
+
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("[meshing] Compute Pass"),
+                timestamp_writes: None
+            });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            let threads = chunks.len() as u32 * Scene::CHUNK_SIZE.pow(3) as u32;
+            cpass.dispatch_workgroups(threads.div_ceil(Self::WORKGROUP_SIZE), 1, 1);
+        }
+
+        encoder.copy_buffer_to_buffer(&self.count_buffer, 0, &readback_buffer, 0, 4);
+        encoder.copy_buffer_to_buffer(self.face_buffer.buffer(), 0, &readback_buffer, 4, faces_size);
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        if let Err(e) = receiver.recv().unwrap_or(Err(wgpu::BufferAsyncError)) {
+            log::error!("Failed to read back meshed faces: {e}");
+            return vec![];
+        }
+
+        let data = slice.get_mapped_range();
+        let count = u32::from_le_bytes(data[..4].try_into().unwrap()).min(capacity) as usize;
+        let faces = bytemuck::cast_slice(&data[4..4 + count * face_size as usize]).to_vec();
+        drop(data);
+        readback_buffer.unmap();
+
+        faces
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+// The CPU path of `Scene::update` is the reference, on random worlds spanning several chunks.
+#[cfg(test)]
+mod tests {
+    use super::common::World;
+    use crate::terrain::{BlockModel, Scene, TextureId};
+
+    // Across chunk borders, including negative coordinates.
+    const MIN: i32 = -20;
+    const MAX: i32 = 20;
+
+    fn models() -> Vec<BlockModel> {
+        let face = |i: u8| TextureId::new(i);
+        vec![
+            BlockModel { faces: [None; 6] },
+            BlockModel { faces: [face(1); 6] },
+            BlockModel { faces: [face(2), face(3), face(4), face(5), face(6), face(7)] },
+            // Only some faces, so that neighbours show faces into it.
+            BlockModel { faces: [face(8), None, None, face(9), None, face(10)] },
+            BlockModel { faces: [None, face(11), face(12), None, face(13), None] }
+        ]
+    }
+
+    // Random models for the blocks at `coords`, mostly empty.
+    fn random_blocks(rng: &mut u32, coords: impl IntoIterator<Item = [i32; 3]>) -> Vec<([i32; 3], usize)> {
+        coords.into_iter().map(|coord| {
+            *rng ^= *rng << 13;
+            *rng ^= *rng >> 17;
+            *rng ^= *rng << 5;
+            (coord, match *rng % 8 {
+                i @ 1..=4 => i as usize,
+                _ => 0
+            })
+        }).collect()
+    }
+
+    fn boxed(min: [i32; 3], max: [i32; 3]) -> impl Iterator<Item = [i32; 3]> {
+        (min[0]..max[0]).flat_map(move |x| (min[1]..max[1]).flat_map(move |y| (min[2]..max[2]).map(move |z| [x, y, z])))
+    }
+
+    // Faces have no order or comparison of their own.
+    fn sorted_faces(scene: &Scene) -> Vec<[u32; 7]> {
+        let mut faces: Vec<[u32; 7]> = scene.faces().map(|&face| bytemuck::cast(face)).collect();
+        faces.sort();
+        faces
+    }
+
+    #[test]
+    fn gpu_meshing_matches_cpu() {
+        let (device, queue) = super::common::device();
+
+        for seed in [1, 0x2545f491] {
+            let mut cpu_scene = Scene::new(&device);
+            let mut gpu_scene = Scene::new(&device);
+            assert!(gpu_scene.set_gpu_meshing(&device, true), "the fallback adapter has no compute shaders");
+
+            let mut rng = seed;
+            let mut world = World::new(models(), random_blocks(&mut rng, boxed([MIN; 3], [MAX; 3]))).lit();
+            let mut check = |world: &World, step: &str| {
+                cpu_scene.update(&device, &queue, world);
+                gpu_scene.update(&device, &queue, world);
+                let faces = sorted_faces(&cpu_scene);
+                assert!(!faces.is_empty());
+                assert_eq!(faces, sorted_faces(&gpu_scene), "seed {seed:#x}, {step}");
+            };
+            check(&world, "whole world");
+
+            // A slab through chunk borders, big enough to be meshed on the GPU again.
+            world.set(random_blocks(&mut rng, boxed([-12, 10, -12], [12, 18, 12])));
+            check(&world, "after a bulk update");
+
+            // A few scattered blocks, meshed on the CPU into the chunks the GPU built.
+            world.set(random_blocks(&mut rng, (0..32).map(|i| [MIN + i, MIN + (i * 7) % 40, MAX - 1 - (i * 3) % 40])));
+            check(&world, "after an edit");
+        }
+    }
+}
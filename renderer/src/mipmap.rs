@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use wgpu::util::DeviceExt;
+
+// The previous level, and the next one for compute, as `mipmap.wgsl` reads and writes them.
+// Array layers are selected in the shader, as GL can't view a layer of an array as a 2D texture.
+const SOURCE_2D: &str = "
+@group(0) @binding(0) var src: texture_2d<f32>;
+fn load_src(p: vec2<u32>) -> vec4<f32> { return textureLoad(src, p, 0); }
+fn src_size() -> vec2<u32> { return textureDimensions(src); }
+";
+const SOURCE_ARRAY: &str = "
+@group(0) @binding(0) var src: texture_2d_array<f32>;
+@group(0) @binding(2) var<uniform> layer: vec4<u32>;
+fn load_src(p: vec2<u32>) -> vec4<f32> { return textureLoad(src, p, layer.x, 0); }
+fn src_size() -> vec2<u32> { return textureDimensions(src); }
+";
+const DESTINATION_2D: &str = "
+@group(0) @binding(1) var dst: texture_storage_2d<FORMAT, write>;
+fn store_dst(p: vec2<u32>, value: vec4<f32>) { textureStore(dst, p, value); }
+fn dst_size() -> vec2<u32> { return textureDimensions(dst); }
+";
+const DESTINATION_ARRAY: &str = "
+@group(0) @binding(1) var dst: texture_storage_2d_array<FORMAT, write>;
+fn store_dst(p: vec2<u32>, value: vec4<f32>) { textureStore(dst, p, layer.x, value); }
+fn dst_size() -> vec2<u32> { return textureDimensions(dst); }
+";
+
+// Selects the shader variant of a pipeline.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct PipelineKey {
+    format: wgpu::TextureFormat,
+    alpha_weighted: bool,
+    gamma_correct: bool,
+    array: bool
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MipmapOptions {
+    // Averages colours weighted by alpha, so that transparent texels, whose colour is usually
+    // meaningless, don't bleed into the edges of cutouts.
+    pub alpha_weighted: bool,
+    // Filters in linear space, for non-sRGB formats holding sRGB colours. Views of sRGB formats
+    // are always filtered in linear space.
+    pub gamma_correct: bool,
+    // Uses a compute pass for formats that can be written as storage textures, if the texture
+    // has `STORAGE_BINDING` usage and the device supports compute shaders.
+    pub compute: bool
+}
+
+// Fills the mip levels of 2D textures, every array layer or cube face, from level 0. Pipelines
+// are created once per format and options, so keep the generator around.
+//
+// The render path draws each level into a separate texture and copies it over, which needs
+// `COPY_DST` usage. Rendering straight into a level of the texture that is sampled drops the
+// draw on GL, where views of the same texture share their mip range.
+pub struct MipmapGenerator {
+    render_pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>,
+    compute_pipelines: HashMap<PipelineKey, wgpu::ComputePipeline>
+}
+
+impl MipmapGenerator {
+    const WORKGROUP_SIZE: u32 = 8;
+
+    pub fn new() -> Self {
+        Self {
+            render_pipelines: HashMap::new(),
+            compute_pipelines: HashMap::new()
+        }
+    }
+
+    fn create_shader(device: &wgpu::Device, key: PipelineKey, storage_format: Option<&str>) -> wgpu::ShaderModule {
+        let mut source = format!(
+            "const ALPHA_WEIGHTED = {};\nconst GAMMA_CORRECT = {};\n{}{}",
+            key.alpha_weighted,
+            key.gamma_correct && !key.format.is_srgb(),
+            if key.array { SOURCE_ARRAY } else { SOURCE_2D },
+            include_str!("shaders/mipmap.wgsl")
+        );
+        if let Some(storage_format) = storage_format {
+            source += &if key.array { DESTINATION_ARRAY } else { DESTINATION_2D }.replace("FORMAT", storage_format);
+            source += include_str!("shaders/mipmap_compute.wgsl");
+        }
+
+        device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[mipmap] Shader"),
+            source: wgpu::ShaderSource::Wgsl(source.into())
+        })
+    }
+
+    // The WGSL name of `format` if it can be written as a storage texture.
+    fn storage_format(device: &wgpu::Device, texture: &wgpu::Texture) -> Option<&'static str> {
+        if !texture.usage().contains(wgpu::TextureUsages::STORAGE_BINDING)
+            || device.limits().max_compute_invocations_per_workgroup < Self::WORKGROUP_SIZE * Self::WORKGROUP_SIZE {
+            return None;
+        }
+
+        let format = texture.format();
+        let features = format.guaranteed_format_features(device.features());
+        if !features.allowed_usages.contains(wgpu::TextureUsages::STORAGE_BINDING) {
+            return None;
+        }
+
+        match format {
+            wgpu::TextureFormat::Rgba8Unorm => Some("rgba8unorm"),
+            wgpu::TextureFormat::Rgba8Snorm => Some("rgba8snorm"),
+            wgpu::TextureFormat::Rgba16Float => Some("rgba16float"),
+            wgpu::TextureFormat::R32Float => Some("r32float"),
+            wgpu::TextureFormat::Rg32Float => Some("rg32float"),
+            wgpu::TextureFormat::Rgba32Float => Some("rgba32float"),
+            _ => None
+        }
+    }
+
+    // Records the passes into `encoder`.
+    pub fn generate(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, options: &MipmapOptions) {
+        assert_eq!(texture.dimension(), wgpu::TextureDimension::D2, "only 2D textures are supported");
+        assert_eq!(texture.sample_count(), 1, "multisampled textures have no mipmaps");
+
+        let key = PipelineKey {
+            format: texture.format(),
+            alpha_weighted: options.alpha_weighted,
+            gamma_correct: options.gamma_correct,
+            array: texture.depth_or_array_layers() > 1
+        };
+
+        // Selects the layer for array textures, a buffer each.
+        let layer_buffers: Vec<_> = (0..texture.depth_or_array_layers()).filter(|_| key.array).map(|layer| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&format!("[mipmap] Layer Buffer {layer}")),
+                contents: bytemuck::cast_slice(&[layer, 0, 0, 0]),
+                usage: wgpu::BufferUsages::UNIFORM
+            })
+        }).collect();
+
+        match Self::storage_format(device, texture).filter(|_| options.compute) {
+            Some(storage_format) => {
+                let pipeline = self.compute_pipelines.entry(key).or_insert_with(|| {
+                    let shader = Self::create_shader(device, key, Some(storage_format));
+                    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                        label: Some("[mipmap] Compute Pipeline"),
+                        layout: None,
+                        module: &shader,
+                        entry_point: "cs_main"
+                    })
+                });
+                Self::generate_compute(device, encoder, texture, pipeline, &layer_buffers);
+            }
+            None => {
+                let pipeline = self.render_pipelines.entry(key).or_insert_with(|| {
+                    let shader = Self::create_shader(device, key, None);
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: Some("[mipmap] Render Pipeline"),
+                        layout: None,
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: &[]
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: "fs_main",
+                            targets: &[Some(key.format.into())]
+                        }),
+                        primitive: Default::default(),
+                        depth_stencil: None,
+                        multisample: Default::default(),
+                        multiview: None
+                    })
+                });
+                Self::generate_render(device, encoder, texture, pipeline, &layer_buffers);
+            }
+        }
+    }
+
+    // All layers of a level, as arrays for array textures.
+    fn level_view(texture: &wgpu::Texture, level: u32) -> wgpu::TextureView {
+        let array = texture.depth_or_array_layers() > 1;
+        texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some(&format!("[mipmap] View Level {level}")),
+            format: None,
+            dimension: Some(if array { wgpu::TextureViewDimension::D2Array } else { wgpu::TextureViewDimension::D2 }),
+            aspect: wgpu::TextureAspect::All,
+            base_mip_level: level,
+            mip_level_count: Some(1),
+            base_array_layer: 0,
+            array_layer_count: None
+        })
+    }
+
+    fn bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        src: &wgpu::TextureView,
+        dst: Option<&wgpu::TextureView>,
+        layer_buffer: Option<&wgpu::Buffer>
+    ) -> wgpu::BindGroup {
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(src)
+        }];
+        if let Some(dst) = dst {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(dst)
+            });
+        }
+        if let Some(layer_buffer) = layer_buffer {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 2,
+                resource: layer_buffer.as_entire_binding()
+            });
+        }
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[mipmap] Bind Group"),
+            layout,
+            entries: &entries
+        })
+    }
+
+    fn generate_render(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, pipeline: &wgpu::RenderPipeline, layer_buffers: &[wgpu::Buffer]) {
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+        for level in 1..texture.mip_level_count() {
+            let size = texture.size().mip_level_size(level, texture.dimension());
+            let target = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&format!("[mipmap] Target Level {level}")),
+                size: wgpu::Extent3d { depth_or_array_layers: 1, ..size },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: texture.format(),
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[]
+            });
+            let target_view = target.create_view(&Default::default());
+            let src = Self::level_view(texture, level - 1);
+
+            for layer in 0..texture.depth_or_array_layers() {
+                let bind_group = Self::bind_group(device, &bind_group_layout, &src, None, layer_buffers.get(layer as usize));
+
+                {
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some(&format!("[mipmap] Render Pass Level {level} Layer {layer}")),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &target_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                                store: wgpu::StoreOp::Store
+                            }
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None
+                    });
+
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_bind_group(0, &bind_group, &[]);
+                    render_pass.draw(0..3, 0..1);
+                }
+
+                encoder.copy_texture_to_texture(target.as_image_copy(), wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d { x: 0, y: 0, z: layer },
+                    aspect: wgpu::TextureAspect::All
+                }, wgpu::Extent3d { depth_or_array_layers: 1, ..size });
+            }
+        }
+    }
+
+    fn generate_compute(device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture, pipeline: &wgpu::ComputePipeline, layer_buffers: &[wgpu::Buffer]) {
+        let bind_group_layout = pipeline.get_bind_group_layout(0);
+
+        for level in 1..texture.mip_level_count() {
+            let size = texture.size().mip_level_size(level, texture.dimension());
+            let src = Self::level_view(texture, level - 1);
+            let dst = Self::level_view(texture, level);
+
+            for layer in 0..texture.depth_or_array_layers() {
+                let bind_group = Self::bind_group(device, &bind_group_layout, &src, Some(&dst), layer_buffers.get(layer as usize));
+
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some(&format!("[mipmap] Compute Pass Level {level} Layer {layer}")),
+                    timestamp_writes: None
+                });
+                compute_pass.set_pipeline(pipeline);
+                compute_pass.set_bind_group(0, &bind_group, &[]);
+                compute_pass.dispatch_workgroups(size.width.div_ceil(Self::WORKGROUP_SIZE), size.height.div_ceil(Self::WORKGROUP_SIZE), 1);
+            }
+        }
+    }
+}
+
+impl Default for MipmapGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,331 @@
+use cgmath::ElementWise;
+
+use crate::terrain::{BlockModel, IntCoord, TextureId, WorldInterface};
+
+#[derive(Clone, Copy)]
+pub struct Particle {
+    pub pos: cgmath::Point3<f32>,
+    pub vel: cgmath::Vector3<f32>,
+    pub gravity: f32,
+    pub age: f32,
+    pub lifetime: f32,
+    pub size: f32,
+    pub texture: TextureId,
+    // Sub-rectangle of the texture tile, as (u0, v0, u1, v1) in [0, 1].
+    pub tile_rect: [f32; 4]
+}
+
+pub struct Emitter {
+    pub origin: cgmath::Point3<f32>,
+    // Particles spawn uniformly within `origin` ± `extent`.
+    pub extent: cgmath::Vector3<f32>,
+    // Particles per second.
+    pub rate: f32,
+    pub velocity: cgmath::Vector3<f32>,
+    pub spread: f32,
+    pub gravity: f32,
+    pub lifetime: f32,
+    pub size: f32,
+    pub texture: TextureId
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct EmitterId(usize);
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ParticleInstance {
+    pos: [f32; 3],
+    size: f32,
+    uv_rect: [f32; 4]
+}
+
+pub struct ParticleSystem {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    sample_count: u32,
+    reversed_z: bool,
+    particles: Vec<Particle>,
+    emitters: Vec<Option<(Emitter, f32)>>,
+    instances: Vec<ParticleInstance>,
+    buffer: crate::utils::DynamicBuffer,
+    rng: u32
+}
+
+impl ParticleSystem {
+    pub const MAX_PARTICLES: usize = 16384;
+    const FRAGMENTS_PER_AXIS: u32 = 4;
+    const GROUND_FRICTION: f32 = 0.7;
+
+    // Bind groups are set by the caller, the same as for `terrain::TerrainRenderer`.
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        texture_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment_layout: &wgpu::BindGroupLayout
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[particle] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/environment.wgsl"),
+                include_str!("shaders/particle.wgsl")
+            ).into())
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[particle] Render Pipeline Layout"),
+            bind_group_layouts: &[
+                texture_layout,
+                camera_layout,
+                environment_layout
+            ],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, format, depth_format, sample_count, false);
+
+        // Bursts from breaking blocks shouldn't keep a large buffer around.
+        let mut buffer = crate::utils::DynamicBuffer::new(
+            device,
+            "[particle] Instance Buffer".into(),
+            512,
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
+        );
+        buffer.set_shrink_policy(crate::utils::ShrinkPolicy::BelowQuarter);
+
+        Self {
+            pipeline,
+            pipeline_layout,
+            shader,
+            format,
+            depth_format,
+            sample_count,
+            reversed_z: false,
+            particles: vec![],
+            emitters: vec![],
+            instances: vec![],
+            buffer,
+            rng: 0x12345678
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        reversed_z: bool
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[particle] Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<ParticleInstance>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32, 2 => Float32x4]
+                }]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: Default::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: if reversed_z { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less },
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None
+        })
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, self.depth_format, sample_count, self.reversed_z);
+    }
+
+    // Has to match `camera::Projection::reversed_z` of the rendered camera.
+    pub fn set_reversed_z(&mut self, device: &wgpu::Device, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, self.depth_format, self.sample_count, reversed_z);
+    }
+
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_signed(&mut self) -> f32 {
+        self.random() * 2.0 - 1.0
+    }
+
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.particles.len() < Self::MAX_PARTICLES {
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn add_emitter(&mut self, emitter: Emitter) -> EmitterId {
+        self.emitters.push(Some((emitter, 0.0)));
+        EmitterId(self.emitters.len() - 1)
+    }
+
+    pub fn remove_emitter(&mut self, id: EmitterId) {
+        self.emitters[id.0] = None;
+    }
+
+    pub fn emitter_mut(&mut self, id: EmitterId) -> Option<&mut Emitter> {
+        self.emitters[id.0].as_mut().map(|(emitter, _)| emitter)
+    }
+
+    // Spawns fragments of `block` at `coord`, each showing a random part of one of its faces.
+    pub fn spawn_block_break(&mut self, coord: &IntCoord, block: &BlockModel) {
+        let textures: Vec<_> = block.faces.iter().flatten().copied().collect();
+        if textures.is_empty() { return; }
+
+        let n = Self::FRAGMENTS_PER_AXIS;
+        let center = cgmath::Point3::new(coord.0[0] as f32 + 0.5, coord.0[1] as f32 + 0.5, coord.0[2] as f32 + 0.5);
+
+        for i in 0..n * n * n {
+            let local = cgmath::Vector3::new(i % n, i / n % n, i / n / n)
+                .map(|v| (v as f32 + 0.5) / n as f32 - 0.5);
+            let texture = textures[(self.random() * textures.len() as f32) as usize % textures.len()];
+            let u = (self.random() * 4.0).floor() / 4.0;
+            let v = (self.random() * 4.0).floor() / 4.0;
+            let jitter = cgmath::Vector3::new(self.random_signed(), self.random(), self.random_signed());
+            let lifetime = 0.5 + self.random();
+            let size = 0.1 + self.random() * 0.1;
+
+            self.spawn(Particle {
+                pos: center + local,
+                vel: local * 4.0 + jitter * 1.5,
+                gravity: 16.0,
+                age: 0.0,
+                lifetime,
+                size,
+                texture,
+                tile_rect: [u, v, u + 0.25, v + 0.25]
+            });
+        }
+    }
+
+    fn emit(&mut self, secs: f32) {
+        let mut emitters = std::mem::take(&mut self.emitters);
+
+        for (emitter, accumulator) in emitters.iter_mut().flatten() {
+            *accumulator += emitter.rate * secs;
+            while *accumulator >= 1.0 {
+                *accumulator -= 1.0;
+                let offset = cgmath::Vector3::new(self.random_signed(), self.random_signed(), self.random_signed());
+                let spread = cgmath::Vector3::new(self.random_signed(), self.random_signed(), self.random_signed());
+                let lifetime = emitter.lifetime * (0.75 + self.random() * 0.5);
+                self.spawn(Particle {
+                    pos: emitter.origin + emitter.extent.mul_element_wise(offset),
+                    vel: emitter.velocity + spread * emitter.spread,
+                    gravity: emitter.gravity,
+                    age: 0.0,
+                    lifetime,
+                    size: emitter.size,
+                    texture: emitter.texture,
+                    tile_rect: [0.0, 0.0, 1.0, 1.0]
+                });
+            }
+        }
+
+        self.emitters = emitters;
+    }
+
+    fn is_solid(world: &impl WorldInterface, pos: cgmath::Point3<f32>) -> bool {
+        let coord = IntCoord([pos.x.floor() as i32, pos.y.floor() as i32, pos.z.floor() as i32]);
+        world.get_block(&coord).faces.iter().any(Option::is_some)
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &impl WorldInterface, secs: f32) {
+        self.emit(secs);
+
+        self.particles.retain_mut(|particle| {
+            particle.age += secs;
+            if particle.age >= particle.lifetime { return false; }
+
+            particle.vel.y -= particle.gravity * secs;
+
+            // Moves one axis at a time, so particles slide along the blocks they hit.
+            let step = particle.vel * secs;
+            for axis in 0..3 {
+                let mut next = particle.pos;
+                next[axis] += step[axis];
+                if Self::is_solid(world, next) {
+                    if axis == 1 {
+                        particle.vel.x *= Self::GROUND_FRICTION;
+                        particle.vel.z *= Self::GROUND_FRICTION;
+                    }
+                    particle.vel[axis] = 0.0;
+                } else {
+                    particle.pos = next;
+                }
+            }
+
+            true
+        });
+
+        self.instances = self.particles.iter().map(|particle| {
+            let tile = particle.texture.get() as u32;
+            let (tile_u, tile_v) = ((tile & 15) as f32, (tile >> 4) as f32);
+            let [u0, v0, u1, v1] = particle.tile_rect;
+            ParticleInstance {
+                pos: particle.pos.into(),
+                size: particle.size,
+                uv_rect: [(tile_u + u0) / 16.0, (tile_v + v0) / 16.0, (tile_u + u1) / 16.0, (tile_v + v1) / 16.0]
+            }
+        }).collect();
+
+        if !self.instances.is_empty() {
+            self.buffer.update(device, queue, bytemuck::cast_slice(&self.instances));
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.instances.clear();
+    }
+
+    pub fn render<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        if self.instances.is_empty() { return; }
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_vertex_buffer(0, self.buffer.buffer().slice(..));
+        rpass.draw(0..6, 0..self.instances.len() as u32);
+    }
+}
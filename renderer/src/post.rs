@@ -0,0 +1,454 @@
+// Format of the target the scene is rendered into, see `PostProcessor::hdr_view`.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Edge length of the LUT used until `PostProcessor::set_lut` is called.
+pub const DEFAULT_LUT_SIZE: u32 = 16;
+
+// Horizontal and vertical blur pairs applied to the bloom texture.
+const BLOOM_BLUR_PASSES: usize = 2;
+
+pub struct PostSettings {
+    pub exposure: f32,
+    pub tone_mapping: bool,
+    pub bloom: bool,
+    // Brightness, before exposure, above which colours bloom.
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub fxaa: bool,
+    pub color_grading: bool
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            tone_mapping: true,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.6,
+            fxaa: true,
+            color_grading: false
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct PostUniform {
+    tone: [f32; 4],
+    bloom: [f32; 4]
+}
+
+impl PostUniform {
+    fn new(settings: &PostSettings) -> Self {
+        Self {
+            tone: [settings.exposure, settings.tone_mapping as u32 as f32, settings.color_grading as u32 as f32, 0.0],
+            bloom: [settings.bloom_threshold, settings.bloom_intensity, settings.bloom as u32 as f32, 0.0]
+        }
+    }
+}
+
+// A colour cube of `size`^3 texels that leaves colours unchanged, with red varying fastest and
+// blue slowest, which is the layout `PostProcessor::set_lut` expects.
+pub fn identity_lut(size: u32) -> Vec<u8> {
+    let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                data.extend([r, g, b].map(|v| (v * 255 / (size - 1)) as u8));
+                data.push(255);
+            }
+        }
+    }
+    data
+}
+
+// Everything the size dependent bind groups are created from.
+struct Bindings {
+    source_layout: wgpu::BindGroupLayout,
+    composite_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    lut_view: wgpu::TextureView
+}
+
+struct Targets {
+    width: u32,
+    height: u32,
+    hdr: wgpu::Texture,
+    hdr_view: wgpu::TextureView,
+    ldr_view: wgpu::TextureView,
+    bloom_views: [wgpu::TextureView; 2],
+    hdr_bind_group: wgpu::BindGroup,
+    bloom_bind_groups: [wgpu::BindGroup; 2],
+    ldr_bind_group: wgpu::BindGroup,
+    composite_bind_group: wgpu::BindGroup
+}
+
+impl Bindings {
+    fn create_lut(device: &wgpu::Device, queue: &wgpu::Queue, size: u32, data: &[u8]) -> wgpu::TextureView {
+        let extent = wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[post] LUT Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+
+        queue.write_texture(wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All
+        }, data, wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size),
+            rows_per_image: Some(size)
+        }, extent);
+
+        texture.create_view(&Default::default())
+    }
+
+    fn create_texture(device: &wgpu::Device, label: &str, format: wgpu::TextureFormat, width: u32, height: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // The HDR texture is also the frame copied by reflective water.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[]
+        })
+    }
+
+    fn create_source_bind_group(&self, device: &wgpu::Device, label: &str, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(label),
+            layout: &self.source_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.buffer.as_entire_binding()
+                }
+            ]
+        })
+    }
+
+    fn create_targets(&self, device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> Targets {
+        let hdr = Self::create_texture(device, "[post] HDR Texture", HDR_FORMAT, width, height);
+        let hdr_view = hdr.create_view(&Default::default());
+
+        let ldr_view = Self::create_texture(device, "[post] LDR Texture", format, width, height)
+            .create_view(&Default::default());
+
+        let bloom_views = [0, 1].map(|i| {
+            Self::create_texture(device, &format!("[post] Bloom Texture {i}"), HDR_FORMAT, (width / 2).max(1), (height / 2).max(1))
+                .create_view(&Default::default())
+        });
+
+        let hdr_bind_group = self.create_source_bind_group(device, "[post] HDR Bind Group", &hdr_view);
+        let bloom_bind_groups = [0, 1].map(|i| {
+            self.create_source_bind_group(device, &format!("[post] Bloom Bind Group {i}"), &bloom_views[i])
+        });
+        let ldr_bind_group = self.create_source_bind_group(device, "[post] LDR Bind Group", &ldr_view);
+
+        let composite_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[post] Composite Bind Group"),
+            layout: &self.composite_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&hdr_view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.buffer.as_entire_binding()
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&bloom_views[0])
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&self.lut_view)
+                }
+            ]
+        });
+
+        Targets {
+            width,
+            height,
+            hdr,
+            hdr_view,
+            ldr_view,
+            bloom_views,
+            hdr_bind_group,
+            bloom_bind_groups,
+            ldr_bind_group,
+            composite_bind_group
+        }
+    }
+}
+
+// Renders the scene from an HDR target to the output: bloom, exposure and tone mapping, colour
+// grading and FXAA, in that order. Anything drawn to the output afterwards, like the crosshair
+// or the GUI, is left untouched.
+pub struct PostProcessor {
+    bright_pipeline: wgpu::RenderPipeline,
+    blur_h_pipeline: wgpu::RenderPipeline,
+    blur_v_pipeline: wgpu::RenderPipeline,
+    composite_pipeline: wgpu::RenderPipeline,
+    fxaa_pipeline: wgpu::RenderPipeline,
+    bindings: Bindings,
+    targets: Targets,
+    format: wgpu::TextureFormat,
+    bloom: bool,
+    fxaa: bool
+}
+
+impl PostProcessor {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat, width: u32, height: u32) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/post.wgsl"));
+
+        let source_entries = [
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true }
+                },
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }
+        ];
+
+        let source_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[post] Source Bind Group Layout"),
+            entries: &source_entries
+        });
+
+        let composite_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[post] Composite Bind Group Layout"),
+            entries: &[
+                source_entries[0],
+                source_entries[1],
+                source_entries[2],
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true }
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true }
+                    },
+                    count: None
+                }
+            ]
+        });
+
+        let source_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[post] Source Pipeline Layout"),
+            bind_group_layouts: &[&source_layout],
+            push_constant_ranges: &[]
+        });
+
+        let composite_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[post] Composite Pipeline Layout"),
+            bind_group_layouts: &[&composite_layout],
+            push_constant_ranges: &[]
+        });
+
+        let create_pipeline = |label: &str, layout: &wgpu::PipelineLayout, entry_point: &str, format: wgpu::TextureFormat| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[]
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(format.into())]
+                }),
+                primitive: Default::default(),
+                depth_stencil: None,
+                multisample: Default::default(),
+                multiview: None
+            })
+        };
+
+        let bright_pipeline = create_pipeline("[post] Bright Pipeline", &source_pipeline_layout, "fs_bright", HDR_FORMAT);
+        let blur_h_pipeline = create_pipeline("[post] Horizontal Blur Pipeline", &source_pipeline_layout, "fs_blur_h", HDR_FORMAT);
+        let blur_v_pipeline = create_pipeline("[post] Vertical Blur Pipeline", &source_pipeline_layout, "fs_blur_v", HDR_FORMAT);
+        let composite_pipeline = create_pipeline("[post] Composite Pipeline", &composite_pipeline_layout, "fs_composite", format);
+        let fxaa_pipeline = create_pipeline("[post] FXAA Pipeline", &source_pipeline_layout, "fs_fxaa", format);
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("[post] Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let settings = PostSettings::default();
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[post] Uniform Buffer"),
+            size: std::mem::size_of::<PostUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        queue.write_buffer(&buffer, 0, bytemuck::cast_slice(&[PostUniform::new(&settings)]));
+
+        let lut_view = Bindings::create_lut(device, queue, DEFAULT_LUT_SIZE, &identity_lut(DEFAULT_LUT_SIZE));
+
+        let bindings = Bindings {
+            source_layout,
+            composite_layout,
+            sampler,
+            buffer,
+            lut_view
+        };
+
+        let targets = bindings.create_targets(device, format, width, height);
+
+        Self {
+            bright_pipeline,
+            blur_h_pipeline,
+            blur_v_pipeline,
+            composite_pipeline,
+            fxaa_pipeline,
+            bindings,
+            targets,
+            format,
+            bloom: settings.bloom,
+            fxaa: settings.fxaa
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.targets = self.bindings.create_targets(device, self.format, width, height);
+    }
+
+    // `data` holds `size`^3 RGBA texels laid out like `identity_lut`.
+    pub fn set_lut(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: u32, data: &[u8]) {
+        assert_eq!(data.len(), (size * size * size * 4) as usize, "a colour grading LUT of size {size} has {size}^3 RGBA texels");
+        self.bindings.lut_view = Bindings::create_lut(device, queue, size, data);
+        self.resize(device, self.targets.width, self.targets.height);
+    }
+
+    // The scene should be rendered into this view, or resolved into it when multisampling.
+    pub fn hdr_view(&self) -> &wgpu::TextureView {
+        &self.targets.hdr_view
+    }
+
+    pub fn hdr_texture(&self) -> &wgpu::Texture {
+        &self.targets.hdr
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, settings: &PostSettings) {
+        self.bloom = settings.bloom;
+        self.fxaa = settings.fxaa;
+        queue.write_buffer(&self.bindings.buffer, 0, bytemuck::cast_slice(&[PostUniform::new(settings)]));
+    }
+
+    fn pass(encoder: &mut wgpu::CommandEncoder, label: &str, pipeline: &wgpu::RenderPipeline, bind_group: &wgpu::BindGroup, view: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(label),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store
+                }
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None
+        });
+
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+
+    // Overwrites `view`, which has to be of the format given to `new`.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let targets = &self.targets;
+
+        if self.bloom {
+            Self::pass(encoder, "[post] Bright Pass", &self.bright_pipeline, &targets.hdr_bind_group, &targets.bloom_views[0]);
+            for _ in 0..BLOOM_BLUR_PASSES {
+                Self::pass(encoder, "[post] Horizontal Blur Pass", &self.blur_h_pipeline, &targets.bloom_bind_groups[0], &targets.bloom_views[1]);
+                Self::pass(encoder, "[post] Vertical Blur Pass", &self.blur_v_pipeline, &targets.bloom_bind_groups[1], &targets.bloom_views[0]);
+            }
+        }
+
+        let composite_view = if self.fxaa { &targets.ldr_view } else { view };
+        Self::pass(encoder, "[post] Composite Pass", &self.composite_pipeline, &targets.composite_bind_group, composite_view);
+
+        if self.fxaa {
+            Self::pass(encoder, "[post] FXAA Pass", &self.fxaa_pipeline, &targets.ldr_bind_group, view);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+struct CameraUniform {
+    view_proj_mat: mat4x4<f32>,
+    pos: vec4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<uniform> env: EnvironmentUniform;
+
+struct CloudUniform {
+    // x: height, y: thickness, z: cell size, w: opacity
+    params: vec4<f32>
+}
+
+@group(2) @binding(0)
+var<uniform> cloud: CloudUniform;
+
+struct InstanceInput {
+    @location(0) origin: vec2<f32>,
+    @location(1) neighbors: u32
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) view_offset: vec3<f32>,
+    @location(1) face_factor: f32
+}
+
+fn cloud_vertex(local: vec3<f32>, origin: vec2<f32>, direction: u32) -> VertexOutput {
+    var out: VertexOutput;
+
+    let size = vec3(cloud.params.z, cloud.params.y, cloud.params.z);
+    let pos = vec3(origin.x, cloud.params.x, origin.y) + local * size;
+
+    out.position = camera.view_proj_mat * vec4(pos, 1.0);
+    out.view_offset = pos - camera.pos.xyz;
+    out.face_factor = env.face_factors[direction >> 2u][direction & 3u];
+
+    return out;
+}
+
+@vertex
+fn vs_box(@builtin(vertex_index) idx: u32, instance: InstanceInput) -> VertexOutput {
+    let direction = idx / 6u;
+
+    // Side faces shared with a neighbouring cloud cell are collapsed.
+    if (instance.neighbors >> direction & 1u) != 0u {
+        var out: VertexOutput;
+        out.position = vec4(0.0, 0.0, 0.0, 1.0);
+        return out;
+    }
+
+    let vertex = face_vertex(idx % 6u, vec3(0), direction);
+    return cloud_vertex(vertex.pos, instance.origin, direction);
+}
+
+@vertex
+fn vs_flat(@builtin(vertex_index) idx: u32, instance: InstanceInput) -> VertexOutput {
+    let vertex = face_vertex(idx, vec3(0), 3u);
+    return cloud_vertex(vertex.pos * vec3(1.0, 0.5, 1.0), instance.origin, 3u);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let brightness = max(env.light.x, env.light.y) * in.face_factor;
+    let fog = fog_factor(length(in.view_offset));
+    let color = mix(vec3(brightness), env.fog_color.rgb, fog);
+    return vec4(color, cloud.params.w * (1.0 - fog));
+}
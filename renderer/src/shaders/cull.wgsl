@@ -0,0 +1,48 @@
+struct Chunk {
+    min: vec3<f32>,
+    first_instance: u32,
+    max: vec3<f32>,
+    instance_count: u32
+}
+
+struct DrawIndirectArgs {
+    vertex_count: u32,
+    instance_count: u32,
+    first_vertex: u32,
+    first_instance: u32
+}
+
+struct CullUniform {
+    // Inside where dot(xyz, p) + w >= 0.
+    planes: array<vec4<f32>, 6>,
+    chunk_count: u32
+}
+
+@group(0) @binding(0)
+var<uniform> cull: CullUniform;
+
+@group(0) @binding(1)
+var<storage, read> chunks: array<Chunk>;
+
+@group(0) @binding(2)
+var<storage, read_write> draws: array<DrawIndirectArgs>;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= cull.chunk_count { return; }
+
+    let chunk = chunks[i];
+
+    // The same test as `camera::Frustum::contains_aabb`, with the corner farthest along each normal.
+    var visible = true;
+    for (var p = 0u; p < 6u; p++) {
+        let plane = cull.planes[p];
+        let corner = select(chunk.min, chunk.max, plane.xyz >= vec3(0.0));
+        if dot(plane.xyz, corner) + plane.w < 0.0 {
+            visible = false;
+        }
+    }
+
+    draws[i] = DrawIndirectArgs(6u, select(0u, chunk.instance_count, visible), 0u, chunk.first_instance);
+}
@@ -0,0 +1,98 @@
+struct CameraUniform {
+    view_proj_mat: mat4x4<f32>,
+    pos: vec4<f32>
+}
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> env: EnvironmentUniform;
+
+@group(0) @binding(0)
+var bones: texture_2d<f32>;
+@group(0) @binding(1)
+var skins: texture_2d_array<f32>;
+@group(0) @binding(2)
+var skin_sampler: sampler;
+
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) part: u32
+}
+
+struct InstanceInput {
+    @location(4) bone_row: u32,
+    @location(5) skin: u32,
+    @location(6) sky_light: f32
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) @interpolate(flat) skin: u32,
+    @location(2) sky_light: f32,
+    @location(3) view_offset: vec3<f32>,
+    @location(4) normal: vec3<f32>
+}
+
+fn bone_matrix(row: u32, part: u32) -> mat4x4<f32> {
+    let coords = vec2(i32(part * 4u), i32(row));
+    return mat4x4(
+        textureLoad(bones, coords, 0),
+        textureLoad(bones, coords + vec2(1, 0), 0),
+        textureLoad(bones, coords + vec2(2, 0), 0),
+        textureLoad(bones, coords + vec2(3, 0), 0)
+    );
+}
+
+@vertex
+fn vs_main(vertex: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let mat = bone_matrix(instance.bone_row, vertex.part);
+    let pos = (mat * vec4(vertex.pos, 1.0)).xyz;
+
+    out.position = camera.view_proj_mat * vec4(pos, 1.0);
+    out.view_offset = pos - camera.pos.xyz;
+    out.normal = normalize((mat * vec4(vertex.normal, 0.0)).xyz);
+    out.tex_coords = vertex.tex_coords;
+    out.skin = instance.skin;
+    out.sky_light = instance.sky_light;
+
+    return out;
+}
+
+const AMBIENT: f32 = 0.5;
+
+fn face_factor(direction: u32) -> f32 {
+    return env.face_factors[direction >> 2u][direction & 3u];
+}
+
+// Rotated parts blend the per-direction factors of the axes their normal leans towards.
+fn blended_face_factor(normal: vec3<f32>) -> f32 {
+    let factors = vec3(
+        select(face_factor(0u), face_factor(1u), normal.x > 0.0),
+        select(face_factor(2u), face_factor(3u), normal.y > 0.0),
+        select(face_factor(4u), face_factor(5u), normal.z > 0.0)
+    );
+    return dot(normal * normal, factors);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(skins, skin_sampler, in.tex_coords, in.skin);
+    if color.a < 0.5 {
+        discard;
+    }
+
+    let normal = normalize(in.normal);
+    let lambert = mix(1.0, max(dot(normal, env.sun_dir.xyz), 0.0), env.shading.x);
+    let lit = in.sky_light * env.light.x * mix(AMBIENT, 1.0, lambert);
+    let brightness = max(lit, env.light.y) * blended_face_factor(normal);
+
+    let fog = fog_factor(length(in.view_offset));
+    return vec4(mix(color.rgb * brightness, env.fog_color.rgb, fog), 1.0);
+}
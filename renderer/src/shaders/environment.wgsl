@@ -0,0 +1,22 @@
+// The layout of `environment::EnvironmentUniform` and the fog it describes; prepended to the
+// shaders that read it, which bind `env` in the group their pipeline gives the environment.
+
+struct EnvironmentUniform {
+    sun_dir: vec4<f32>,
+    zenith_color: vec4<f32>,
+    horizon_color: vec4<f32>,
+    sunset_color: vec4<f32>,
+    light: vec4<f32>,
+    fog_color: vec4<f32>,
+    fog: vec4<f32>,
+    face_factors: array<vec4<f32>, 2>,
+    shading: vec4<f32>
+}
+
+fn fog_factor(dist: f32) -> f32 {
+    if env.fog.w == 0.0 {
+        return clamp((dist - env.fog.x) / (env.fog.y - env.fog.x), 0.0, 1.0);
+    }
+    let d = dist * env.fog.z;
+    return 1.0 - exp(-d * d);
+}
@@ -0,0 +1,32 @@
+// Shared by every pipeline that draws `terrain::Face` instances; prepended to their shaders.
+
+struct FaceVertex {
+    pos: vec3<f32>,
+    corner: vec2<u32>,
+    normal: vec3<f32>
+}
+
+fn face_vertex(idx: u32, coords: vec3<i32>, direction: u32) -> FaceVertex {
+    var out: FaceVertex;
+
+    let dir_axis = direction >> 1u;
+    let dir_offset = direction & 1u;
+
+    var vertex_idx = idx;
+    if vertex_idx >= 3u { vertex_idx = 6u - vertex_idx; }
+
+    var local_pos: vec3<u32>;
+
+    local_pos[dir_axis] = dir_offset;
+    local_pos[dir_axis & 1u ^ 1u] = vertex_idx >> (dir_axis & 1u ^ 1u) & 1u;
+    local_pos[dir_axis & 2u ^ 2u] = vertex_idx >> (dir_axis & 1u) & 1u ^ dir_offset ^ dir_axis >> 1u;
+
+    out.pos = vec3<f32>(coords) + vec3<f32>(local_pos);
+
+    out.corner = vec2(vertex_idx & 1u, vertex_idx >> 1u ^ 1u);
+
+    out.normal = vec3(0.0);
+    out.normal[dir_axis] = f32(dir_offset) * 2.0 - 1.0;
+
+    return out;
+}
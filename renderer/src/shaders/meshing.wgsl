@@ -0,0 +1,89 @@
+// The visible faces of `terrain::Scene::update`, for whole chunks. A thread per block, blocks
+// are given for each chunk with a border of one, so that its neighbours are known.
+
+const CHUNK_SIZE: u32 = 16u;
+const PADDED_SIZE: u32 = 18u;
+
+struct Params {
+    chunk_count: u32,
+    capacity: u32
+}
+
+struct Chunk {
+    origin: vec3<i32>,
+    first_block: u32
+}
+
+struct Block {
+    model: u32,
+    // Sky light in the low byte, emission in the next.
+    light: u32
+}
+
+struct Model {
+    // Texture ids, 0 for no face.
+    faces: array<u32, 6>
+}
+
+// The same layout as `terrain::Face`.
+struct Face {
+    x: i32,
+    y: i32,
+    z: i32,
+    direction: u32,
+    texture: u32,
+    sky_light: u32,
+    emission: u32
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var<storage, read> chunks: array<Chunk>;
+
+@group(0) @binding(2)
+var<storage, read> blocks: array<Block>;
+
+@group(0) @binding(3)
+var<storage, read> models: array<Model>;
+
+@group(0) @binding(4)
+var<storage, read_write> face_count: atomic<u32>;
+
+@group(0) @binding(5)
+var<storage, read_write> faces: array<Face>;
+
+fn block_at(chunk: Chunk, p: vec3<i32>) -> Block {
+    let q = vec3<u32>(p);
+    return blocks[chunk.first_block + (q.z * PADDED_SIZE + q.y) * PADDED_SIZE + q.x];
+}
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let blocks_per_chunk = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+    let chunk_index = id.x / blocks_per_chunk;
+    if chunk_index >= params.chunk_count { return; }
+
+    let chunk = chunks[chunk_index];
+    let local = id.x % blocks_per_chunk;
+    let p = vec3<i32>(vec3(local % CHUNK_SIZE, local / CHUNK_SIZE % CHUNK_SIZE, local / CHUNK_SIZE / CHUNK_SIZE)) + 1;
+    let block = block_at(chunk, p);
+
+    for (var direction = 0u; direction < 6u; direction++) {
+        // Indexed in place, as naga only allows constant indices into array values.
+        let texture = models[block.model].faces[direction];
+        if texture == 0u { continue; }
+
+        var offset = vec3(0);
+        offset[direction >> 1u] = i32(direction & 1u) * 2 - 1;
+        let facing = block_at(chunk, p + offset);
+        if models[facing.model].faces[direction ^ 1u] != 0u { continue; }
+
+        let i = atomicAdd(&face_count, 1u);
+        if i < params.capacity {
+            let coord = chunk.origin + p - 1;
+            faces[i] = Face(coord.x, coord.y, coord.z, direction, texture, facing.light & 0xffu, block.light >> 8u);
+        }
+    }
+}
@@ -1,6 +1,8 @@
+// `ALPHA_WEIGHTED`, `GAMMA_CORRECT` and the bindings of the previous level, with `load_src` and
+// `src_size` to read them, are prepended by `mipmap::MipmapGenerator`.
+
 struct VertexOutput {
-    @builtin(position) position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>
+    @builtin(position) position: vec4<f32>
 }
 
 @vertex
@@ -8,16 +10,48 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
     var out: VertexOutput;
     let coords = vec2(f32(idx & 1u), f32(idx >> 1u)) * 2.0;
     out.position = vec4(coords.x * 2.0 - 1.0, 1.0 - coords.y * 2.0, 0.0, 1.0);
-    out.tex_coords = coords;
     return out;
 }
 
-@group(0) @binding(0)
-var tex: texture_2d<f32>;
-@group(0) @binding(1)
-var tex_sampler: sampler;
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    return select(pow((c + 0.055) / 1.055, vec3(2.4)), c / 12.92, c <= vec3(0.04045));
+}
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    return select(1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, c * 12.92, c <= vec3(0.0031308));
+}
+
+// A box filter over the 2x2 texels of the previous level, clamped at odd edges.
+fn downsample(dst: vec2<u32>) -> vec4<f32> {
+    let size = src_size();
+
+    var color = vec3(0.0);
+    var weighted_color = vec3(0.0);
+    var alpha = 0.0;
+    for (var i = 0u; i < 4u; i++) {
+        let p = min(dst * 2u + vec2(i & 1u, i >> 1u), size - 1u);
+        var texel = load_src(p);
+        if GAMMA_CORRECT {
+            texel = vec4(srgb_to_linear(texel.rgb), texel.a);
+        }
+        color += texel.rgb;
+        weighted_color += texel.rgb * texel.a;
+        alpha += texel.a;
+    }
+
+    // Transparent texels don't darken the others, unless all of them are.
+    if ALPHA_WEIGHTED && alpha > 0.0 {
+        color = weighted_color / alpha;
+    } else {
+        color /= 4.0;
+    }
+    if GAMMA_CORRECT {
+        color = linear_to_srgb(color);
+    }
+    return vec4(color, alpha / 4.0);
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(tex, tex_sampler, in.tex_coords);
+    return downsample(vec2<u32>(in.position.xy));
 }
@@ -0,0 +1,8 @@
+// Appended to `mipmap.wgsl`, after `store_dst` and `dst_size` for the next level.
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= dst_size()) { return; }
+
+    store_dst(id.xy, downsample(id.xy));
+}
@@ -0,0 +1,68 @@
+struct CameraUniform {
+    view_proj_mat: mat4x4<f32>,
+    pos: vec4<f32>
+}
+
+@group(1) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(2) @binding(0)
+var<uniform> env: EnvironmentUniform;
+
+struct InstanceInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) size: f32,
+    @location(2) uv_rect: vec4<f32>
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) view_offset: vec3<f32>
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    var vertex_idx = idx;
+    if vertex_idx >= 3u { vertex_idx = 6u - vertex_idx; }
+    let corner = vec2(f32(vertex_idx & 1u), f32(vertex_idx >> 1u));
+
+    let to_camera = normalize(camera.pos.xyz - instance.pos);
+    // Straight above or below the particle, the camera's own right axis, which the first row of
+    // the view projection matrix points along.
+    var right = cross(vec3(0.0, 1.0, 0.0), to_camera);
+    if dot(right, right) < 1e-6 {
+        let m = camera.view_proj_mat;
+        right = vec3(m[0].x, m[1].x, m[2].x);
+    }
+    right = normalize(right);
+    let up = cross(to_camera, right);
+
+    let offset = (corner - 0.5) * instance.size;
+    let pos = instance.pos + right * offset.x + up * offset.y;
+
+    out.position = camera.view_proj_mat * vec4(pos, 1.0);
+    out.view_offset = pos - camera.pos.xyz;
+    out.tex_coords = mix(instance.uv_rect.xy, instance.uv_rect.zw, vec2(corner.x, 1.0 - corner.y));
+
+    return out;
+}
+
+@group(0) @binding(0)
+var tex: texture_2d<f32>;
+@group(0) @binding(1)
+var tex_sampler: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSampleLevel(tex, tex_sampler, in.tex_coords, 0.0);
+    if color.a < 0.5 {
+        discard;
+    }
+
+    let brightness = max(env.light.x, env.light.y);
+    let fog = fog_factor(length(in.view_offset));
+    return vec4(mix(color.rgb * brightness, env.fog_color.rgb, fog), 1.0);
+}
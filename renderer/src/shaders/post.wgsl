@@ -0,0 +1,143 @@
+struct PostUniform {
+    // x: exposure, y: tone mapping, z: colour grading
+    tone: vec4<f32>,
+    // x: threshold, y: intensity, z: enabled
+    bloom: vec4<f32>
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+@group(0) @binding(1)
+var source_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> post: PostUniform;
+
+// Only bound by the composite pipeline.
+@group(0) @binding(3)
+var bloom_tex: texture_2d<f32>;
+@group(0) @binding(4)
+var lut: texture_3d<f32>;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let coords = vec2(f32(idx & 1u), f32(idx >> 1u)) * 2.0;
+    out.position = vec4(coords.x * 2.0 - 1.0, 1.0 - coords.y * 2.0, 0.0, 1.0);
+    out.uv = coords;
+    return out;
+}
+
+fn sample(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source, source_sampler, uv, 0.0).rgb;
+}
+
+// Keeps what is brighter than the threshold. The target has half the size of the source, so
+// the bilinear sample averages four pixels.
+@fragment
+fn fs_bright(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample(in.uv);
+    let brightness = max(color.r, max(color.g, color.b));
+    let contribution = max(brightness - post.bloom.x, 0.0) / max(brightness, 0.0001);
+    return vec4(color * contribution, 1.0);
+}
+
+// 9-tap gaussian, using bilinear filtering to read two pixels per tap.
+fn blur(uv: vec2<f32>, dir: vec2<f32>) -> vec4<f32> {
+    let texel = dir / vec2<f32>(textureDimensions(source));
+    var color = sample(uv) * 0.2270270270;
+    color += (sample(uv + texel * 1.3846153846) + sample(uv - texel * 1.3846153846)) * 0.3162162162;
+    color += (sample(uv + texel * 3.2307692308) + sample(uv - texel * 3.2307692308)) * 0.0702702703;
+    return vec4(color, 1.0);
+}
+
+@fragment
+fn fs_blur_h(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2(1.0, 0.0));
+}
+
+@fragment
+fn fs_blur_v(in: VertexOutput) -> @location(0) vec4<f32> {
+    return blur(in.uv, vec2(0.0, 1.0));
+}
+
+// Narkowicz's fit of the ACES filmic curve.
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), vec3(0.0), vec3(1.0));
+}
+
+// The LUT maps gamma encoded colours, as the ones graded in image editors are.
+fn grade(color: vec3<f32>) -> vec3<f32> {
+    let size = f32(textureDimensions(lut).x);
+    let encoded = pow(color, vec3(1.0 / 2.2));
+    let coords = (encoded * (size - 1.0) + 0.5) / size;
+    let graded = textureSampleLevel(lut, source_sampler, coords, 0.0).rgb;
+    return pow(graded, vec3(2.2));
+}
+
+@fragment
+fn fs_composite(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = sample(in.uv);
+    if post.bloom.z > 0.0 {
+        color += textureSampleLevel(bloom_tex, source_sampler, in.uv, 0.0).rgb * post.bloom.y;
+    }
+    color *= post.tone.x;
+
+    if post.tone.y > 0.0 {
+        color = aces(color);
+    } else {
+        color = clamp(color, vec3(0.0), vec3(1.0));
+    }
+
+    if post.tone.z > 0.0 {
+        color = grade(color);
+    }
+
+    return vec4(color, 1.0);
+}
+
+fn luma(color: vec3<f32>) -> f32 {
+    return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+const FXAA_REDUCE_MIN: f32 = 0.0078125;
+const FXAA_REDUCE_MUL: f32 = 0.125;
+const FXAA_SPAN_MAX: f32 = 8.0;
+
+@fragment
+fn fs_fxaa(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    let center = sample(in.uv);
+    let l_m = luma(center);
+    let l_nw = luma(sample(in.uv + vec2(-1.0, -1.0) * texel));
+    let l_ne = luma(sample(in.uv + vec2(1.0, -1.0) * texel));
+    let l_sw = luma(sample(in.uv + vec2(-1.0, 1.0) * texel));
+    let l_se = luma(sample(in.uv + vec2(1.0, 1.0) * texel));
+
+    let l_min = min(l_m, min(min(l_nw, l_ne), min(l_sw, l_se)));
+    let l_max = max(l_m, max(max(l_nw, l_ne), max(l_sw, l_se)));
+    if l_max - l_min < max(0.0312, l_max * 0.125) {
+        return vec4(center, 1.0);
+    }
+
+    // Blurs along the edge, perpendicular to the luma gradient.
+    var dir = vec2(-((l_nw + l_ne) - (l_sw + l_se)), (l_nw + l_sw) - (l_ne + l_se));
+    let reduce = max((l_nw + l_ne + l_sw + l_se) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    let scale = 1.0 / (min(abs(dir.x), abs(dir.y)) + reduce);
+    dir = clamp(dir * scale, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * texel;
+
+    let near = 0.5 * (sample(in.uv + dir * (1.0 / 3.0 - 0.5)) + sample(in.uv + dir * (2.0 / 3.0 - 0.5)));
+    let far = near * 0.5 + 0.25 * (sample(in.uv - dir * 0.5) + sample(in.uv + dir * 0.5));
+
+    // The wider blur is rejected when it reaches past the edge.
+    let l_far = luma(far);
+    if l_far < l_min || l_far > l_max {
+        return vec4(near, 1.0);
+    }
+    return vec4(far, 1.0);
+}
@@ -0,0 +1,17 @@
+struct CascadeUniform {
+    light_view_proj_mat: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> cascade: CascadeUniform;
+
+struct InstanceInput {
+    @location(0) coords: vec3<i32>,
+    @location(1) direction: u32
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    let vertex = face_vertex(idx, instance.coords, instance.direction);
+    return cascade.light_view_proj_mat * vec4(vertex.pos, 1.0);
+}
@@ -0,0 +1,86 @@
+struct SkyUniform {
+    inv_view_proj_mat: mat4x4<f32>,
+    depths: vec4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> sky: SkyUniform;
+
+@group(1) @binding(0)
+var<uniform> env: EnvironmentUniform;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) ndc: vec2<f32>
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    var out: VertexOutput;
+    let coords = vec2(f32(idx & 1u), f32(idx >> 1u)) * 2.0;
+    out.position = vec4(coords.x * 2.0 - 1.0, 1.0 - coords.y * 2.0, 0.0, 1.0);
+    out.ndc = out.position.xy;
+    return out;
+}
+
+const SUN_SIZE: f32 = 0.09;
+const MOON_SIZE: f32 = 0.06;
+
+// Returns the sprite-local coordinates in [-1, 1] if `dir` hits the square sprite facing `center`.
+fn sprite_coords(dir: vec3<f32>, center: vec3<f32>, size: f32) -> vec3<f32> {
+    let d = dot(dir, center);
+    if d <= 0.0 {
+        return vec3(0.0);
+    }
+
+    let u = vec3(-center.y, center.x, 0.0);
+    let v = vec3(0.0, 0.0, 1.0);
+    let local = vec2(dot(dir, u), dot(dir, v)) / d / size;
+
+    if abs(local.x) > 1.0 || abs(local.y) > 1.0 {
+        return vec3(0.0);
+    }
+
+    return vec3(local, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if env.fog.w == 2.0 {
+        return vec4(env.fog_color.rgb, 1.0);
+    }
+
+    // From two points on the view ray, as rays of orthographic views don't meet at the camera.
+    let near = sky.inv_view_proj_mat * vec4(in.ndc, sky.depths.x, 1.0);
+    let far = sky.inv_view_proj_mat * vec4(in.ndc, sky.depths.y, 1.0);
+    let dir = normalize(far.xyz / far.w - near.xyz / near.w);
+    let sun_dir = env.sun_dir.xyz;
+
+    let height = clamp(dir.y, 0.0, 1.0);
+    var color = mix(env.horizon_color.rgb, env.zenith_color.rgb, sqrt(height));
+
+    let towards_sun = max(dot(normalize(vec3(dir.x, 0.0, dir.z)), normalize(vec3(sun_dir.x, 0.0, sun_dir.z))), 0.0);
+    let sunset = env.sunset_color.a * pow(towards_sun, 4.0) * (1.0 - sqrt(height));
+    color = mix(color, env.sunset_color.rgb, sunset);
+
+    let sun = sprite_coords(dir, sun_dir, SUN_SIZE);
+    if sun.z > 0.0 {
+        let edge = max(abs(sun.x), abs(sun.y));
+        let glow = 1.0 - smoothstep(0.6, 1.0, edge);
+        color = mix(color, vec3(1.0, 0.95, 0.7), max(glow, 0.3));
+    }
+
+    let moon = sprite_coords(dir, -sun_dir, MOON_SIZE);
+    if moon.z > 0.0 {
+        let cell = vec2<i32>(floor((moon.xy * 0.5 + 0.5) * 4.0));
+        var shade = vec3(0.75, 0.78, 0.85);
+        if (cell.x * 3 + cell.y * 5) % 7 == 0 {
+            shade *= 0.7;
+        }
+        color = shade;
+    }
+
+    color += vec3(1.0, 0.9, 0.6) * pow(max(dot(dir, sun_dir), 0.0), 64.0) * 0.4;
+
+    return vec4(color, 1.0);
+}
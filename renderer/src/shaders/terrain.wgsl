@@ -1,45 +1,51 @@
 struct CameraUniform {
-    view_proj_mat: mat4x4<f32>
+    view_proj_mat: mat4x4<f32>,
+    pos: vec4<f32>
 }
 
 @group(1) @binding(0)
 var<uniform> camera: CameraUniform;
 
+@group(2) @binding(0)
+var<uniform> env: EnvironmentUniform;
+
 struct InstanceInput {
     @location(0) coords: vec3<i32>,
     @location(1) direction: u32,
-    @location(2) tex_id: u32
+    @location(2) tex_id: u32,
+    @location(3) sky_light: u32,
+    @location(4) emission: u32
 }
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) sky_light: f32,
+    @location(2) view_offset: vec3<f32>,
+    @location(3) world_pos: vec3<f32>,
+    @location(4) normal: vec3<f32>,
+    @location(5) face_factor: f32,
+    @location(6) emission: f32,
+    @location(7) @interpolate(flat) tile_origin: vec2<f32>
 }
 
 @vertex
 fn vs_main(@builtin(vertex_index) idx: u32, instance: InstanceInput) -> VertexOutput {
     var out: VertexOutput;
 
-    let dir_axis = instance.direction >> 1u;
-    let dir_offset = instance.direction & 1u;
-
-    var vertex_idx = idx;
-    if vertex_idx >= 3u { vertex_idx = 6u - vertex_idx; }
-
-    var local_pos: vec3<u32>;
+    let vertex = face_vertex(idx, instance.coords, instance.direction);
 
-    local_pos[dir_axis] = dir_offset;
-    local_pos[dir_axis & 1u ^ 1u] = vertex_idx >> (dir_axis & 1u ^ 1u) & 1u;
-    local_pos[dir_axis & 2u ^ 2u] = vertex_idx >> (dir_axis & 1u) & 1u ^ dir_offset ^ dir_axis >> 1u;
+    out.position = camera.view_proj_mat * vec4(vertex.pos, 1.0);
+    out.view_offset = vertex.pos - camera.pos.xyz;
+    out.world_pos = vertex.pos;
+    out.normal = vertex.normal;
 
-    let pos = vec3<f32>(instance.coords) + vec3<f32>(local_pos);
+    out.tile_origin = vec2<f32>(vec2(instance.tex_id & 15u, instance.tex_id >> 4u)) / 16.0;
+    out.tex_coords = out.tile_origin + vec2<f32>(vertex.corner) / 16.0;
 
-    out.position = camera.view_proj_mat * vec4(pos, 1.0);
-
-    out.tex_coords = vec2(
-        f32((instance.tex_id & 15u) + (vertex_idx & 1u)),
-        f32((instance.tex_id >> 4u) + (vertex_idx >> 1u ^ 1u))
-    ) / 16.0;
+    out.sky_light = f32(instance.sky_light) / 15.0;
+    out.emission = f32(instance.emission) / 15.0;
+    out.face_factor = env.face_factors[instance.direction >> 2u][instance.direction & 3u];
 
     return out;
 }
@@ -49,7 +55,89 @@ var tex: texture_2d<f32>;
 @group(0) @binding(1)
 var tex_sampler: sampler;
 
+struct TextureUniform {
+    // x: LOD bias, y: 1 with linear filtering, z: highest level sampled.
+    params: vec4<f32>
+}
+
+@group(0) @binding(2)
+var<uniform> tex_params: TextureUniform;
+
+struct ShadowUniform {
+    light_view_proj_mats: array<mat4x4<f32>, 4>,
+    normal_offsets: vec4<f32>,
+    params: vec4<f32>
+}
+
+@group(3) @binding(0)
+var shadow_map: texture_depth_2d_array;
+@group(3) @binding(1)
+var shadow_sampler: sampler_comparison;
+@group(3) @binding(2)
+var<uniform> shadow: ShadowUniform;
+
+// Share of the sky light that still reaches faces in shadow or turned away from the sun.
+const AMBIENT: f32 = 0.5;
+// Brightness of a face with full emission, beyond what any lit face reaches.
+const EMISSION: f32 = 4.0;
+
+// Returns 1.0 when lit and 0.0 when fully in shadow, using the first cascade that covers `pos`.
+fn sun_visibility(pos: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let cascades = u32(shadow.params.x);
+    let texel = shadow.params.y;
+
+    for (var i = 0u; i < cascades; i++) {
+        let offset = normal * shadow.normal_offsets[i];
+        let clip = shadow.light_view_proj_mats[i] * vec4(pos + offset, 1.0);
+        let ndc = clip.xyz / clip.w;
+        let uv = ndc.xy * vec2(0.5, -0.5) + 0.5;
+
+        if any(uv < vec2(0.0)) || any(uv > vec2(1.0)) || ndc.z > 1.0 {
+            continue;
+        }
+
+        var sum = 0.0;
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let coords = uv + vec2(f32(x), f32(y)) * texel;
+                sum += textureSampleCompareLevel(shadow_map, shadow_sampler, coords, i32(i), ndc.z);
+            }
+        }
+        return sum / 9.0;
+    }
+
+    return 1.0;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(tex, tex_sampler, in.tex_coords);
+    var visibility = 1.0;
+    if shadow.params.z > 0.0 && env.sun_dir.y > 0.0 {
+        visibility = sun_visibility(in.world_pos, in.normal);
+    }
+
+    let lambert = mix(1.0, max(dot(in.normal, env.sun_dir.xyz), 0.0), env.shading.x);
+    let direct = lambert * visibility;
+
+    let lit = in.sky_light * env.light.x * mix(AMBIENT, 1.0, direct);
+    let brightness = max(max(lit, env.light.y) * in.face_factor, in.emission * EMISSION);
+
+    // The gradients of the unclamped coordinates, scaled by the bias as the sampler would.
+    let bias = exp2(tex_params.params.x);
+    let ddx = dpdx(in.tex_coords) * bias;
+    let ddy = dpdy(in.tex_coords) * bias;
+
+    // Linear filtering reads the texels around a sample, which at the edge of a tile belong to
+    // its neighbour, so samples stay half a texel of the sampled level inside.
+    var uv = in.tex_coords;
+    if tex_params.params.y > 0.0 {
+        let size = vec2<f32>(textureDimensions(tex));
+        let lod = clamp(log2(max(length(ddx * size), length(ddy * size))), 0.0, tex_params.params.z);
+        let inset = exp2(lod) * 0.5 / size;
+        uv = clamp(uv, in.tile_origin + inset, in.tile_origin + 1.0 / 16.0 - inset);
+    }
+
+    let color = textureSampleGrad(tex, tex_sampler, uv, ddx, ddy);
+    let fog = fog_factor(length(in.view_offset));
+    return vec4(mix(color.rgb * brightness, env.fog_color.rgb, fog), color.a);
 }
@@ -0,0 +1,211 @@
+struct WaterUniform {
+    view_proj_mat: mat4x4<f32>,
+    inv_view_proj_mat: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+    // rgb: colour, a: absorption per block
+    color: vec4<f32>,
+    // x: time, y: wave height, z: surface level, w: reversed depth
+    params: vec4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> water: WaterUniform;
+
+@group(1) @binding(0)
+var<uniform> env: EnvironmentUniform;
+
+// Only bound by the reflective pipeline.
+@group(2) @binding(0)
+var depth_tex: texture_2d<f32>;
+@group(2) @binding(1)
+var scene_tex: texture_2d<f32>;
+
+struct InstanceInput {
+    @location(0) coords: vec3<i32>,
+    @location(1) direction: u32,
+    @location(2) surface: u32,
+    @location(3) sky_light: u32
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_pos: vec3<f32>,
+    @location(1) @interpolate(flat) direction: u32,
+    @location(2) sky_light: f32
+}
+
+// Returns the height and its x and z derivatives of a single sine wave.
+fn wave(p: vec2<f32>, dir: vec2<f32>, frequency: f32, speed: f32) -> vec3<f32> {
+    let d = normalize(dir);
+    let phase = dot(p, d) * frequency + water.params.x * speed;
+    return vec3(sin(phase), cos(phase) * frequency * d);
+}
+
+fn waves(p: vec2<f32>) -> vec3<f32> {
+    var sum = wave(p, vec2(1.0, 0.3), 1.3, 1.1);
+    sum += wave(p, vec2(-0.4, 1.0), 2.1, 1.7) * 0.5;
+    sum += wave(p, vec2(0.7, -0.8), 3.7, 2.3) * 0.25;
+    return sum * water.params.y;
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    let vertex = face_vertex(idx, instance.coords, instance.direction);
+    var pos = vertex.pos;
+
+    // Lowers the top edge of surface blocks, so water sits below the blocks around it.
+    if instance.surface != 0u && pos.y > f32(instance.coords.y) + 0.5 {
+        pos.y = f32(instance.coords.y) + water.params.z + waves(pos.xz).x;
+    }
+
+    out.position = water.view_proj_mat * vec4(pos, 1.0);
+    out.world_pos = pos;
+    out.direction = instance.direction;
+    out.sky_light = f32(instance.sky_light) / 15.0;
+
+    return out;
+}
+
+fn sky_color(dir: vec3<f32>) -> vec3<f32> {
+    let height = clamp(dir.y, 0.0, 1.0);
+    var color = mix(env.horizon_color.rgb, env.zenith_color.rgb, sqrt(height));
+
+    let sun_dir = env.sun_dir.xyz;
+    let towards_sun = max(dot(normalize(vec3(dir.x, 0.0, dir.z)), normalize(vec3(sun_dir.x, 0.0, sun_dir.z))), 0.0);
+    let sunset = env.sunset_color.a * pow(towards_sun, 4.0) * (1.0 - sqrt(height));
+    return mix(color, env.sunset_color.rgb, sunset);
+}
+
+struct Surface {
+    normal: vec3<f32>,
+    view_dir: vec3<f32>,
+    fresnel: f32,
+    // Water colour lit by the environment, without reflections.
+    color: vec3<f32>,
+    specular: vec3<f32>
+}
+
+fn surface(in: VertexOutput) -> Surface {
+    var out: Surface;
+
+    var normal = vec3(0.0);
+    normal[in.direction >> 1u] = f32(in.direction & 1u) * 2.0 - 1.0;
+    if in.direction == 3u {
+        let slope = waves(in.world_pos.xz).yz;
+        normal = normalize(vec3(-slope.x, 1.0, -slope.y));
+    }
+
+    out.view_dir = normalize(in.world_pos - water.camera_pos.xyz);
+    // Seen from below.
+    if dot(normal, out.view_dir) > 0.0 {
+        normal = -normal;
+    }
+    out.normal = normal;
+
+    let cos_theta = max(dot(normal, -out.view_dir), 0.0);
+    out.fresnel = 0.02 + 0.98 * pow(1.0 - cos_theta, 5.0);
+
+    let brightness = max(in.sky_light * env.light.x, env.light.y);
+    out.color = water.color.rgb * brightness;
+
+    let reflected = reflect(out.view_dir, normal);
+    let daylight = env.sun_dir.w;
+    out.specular = vec3(1.0, 0.95, 0.8) * pow(max(dot(reflected, env.sun_dir.xyz), 0.0), 200.0) * daylight;
+
+    return out;
+}
+
+fn finish(in: VertexOutput, color: vec3<f32>, alpha: f32) -> vec4<f32> {
+    let fog = fog_factor(length(in.world_pos - water.camera_pos.xyz));
+    return vec4(mix(color, env.fog_color.rgb, fog), mix(alpha, 1.0, fog));
+}
+
+@fragment
+fn fs_simple(in: VertexOutput) -> @location(0) vec4<f32> {
+    let s = surface(in);
+    let reflection = sky_color(reflect(s.view_dir, s.normal));
+    let color = mix(s.color, reflection, s.fresnel) + s.specular;
+    return finish(in, color, mix(0.7, 1.0, s.fresnel));
+}
+
+fn unproject(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec3(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth);
+    let pos = water.inv_view_proj_mat * vec4(ndc, 1.0);
+    // With reversed depth the far plane is at infinity.
+    return pos.xyz / max(pos.w, 1e-6);
+}
+
+// Whether depth `a` is farther away than depth `b`.
+fn farther(a: f32, b: f32) -> bool {
+    return select(a > b, a < b, water.params.w != 0.0);
+}
+
+const SSR_STEPS: i32 = 32;
+
+// Marches the reflected ray in world space, comparing against the depth buffer. Returns the
+// colour of the frame where it hits in rgb, with the confidence of the hit in a.
+fn trace_reflection(origin: vec3<f32>, dir: vec3<f32>, size: vec2<f32>) -> vec4<f32> {
+    var t = 0.0;
+    var step = 0.2;
+
+    for (var i = 0; i < SSR_STEPS; i++) {
+        t += step;
+        step *= 1.15;
+
+        let pos = origin + dir * t;
+        let clip = water.view_proj_mat * vec4(pos, 1.0);
+        if clip.w <= 0.0 {
+            break;
+        }
+
+        let ndc = clip.xyz / clip.w;
+        let uv = ndc.xy * vec2(0.5, -0.5) + 0.5;
+        if any(uv < vec2(0.0)) || any(uv >= vec2(1.0)) {
+            break;
+        }
+
+        let pixel = vec2<i32>(uv * size);
+        let depth = textureLoad(depth_tex, pixel, 0).r;
+        if farther(ndc.z, depth) {
+            let hit = unproject(uv, depth);
+            let ray_dist = length(pos - water.camera_pos.xyz);
+            let hit_dist = length(hit - water.camera_pos.xyz);
+            if ray_dist - hit_dist > step * 2.0 {
+                break;
+            }
+
+            let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+            let confidence = smoothstep(0.0, 0.1, edge) * (1.0 - f32(i) / f32(SSR_STEPS));
+            return vec4(textureLoad(scene_tex, pixel, 0).rgb, confidence);
+        }
+    }
+
+    return vec4(0.0);
+}
+
+@fragment
+fn fs_reflective(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(depth_tex));
+    let pixel = vec2<i32>(in.position.xy);
+    let scene_depth = textureLoad(depth_tex, pixel, 0).r;
+    if in.position.z == scene_depth || farther(in.position.z, scene_depth) {
+        discard;
+    }
+
+    let s = surface(in);
+
+    // Opacity grows with the distance light travels through the water to what is behind it.
+    let behind = unproject(in.position.xy / size, scene_depth);
+    let thickness = length(behind - in.world_pos);
+    let alpha = clamp(1.0 - exp(-thickness * water.color.a), 0.15, 1.0);
+
+    let reflected = reflect(s.view_dir, s.normal);
+    let sky = sky_color(reflected);
+    let traced = trace_reflection(in.world_pos, reflected, size);
+    let reflection = mix(sky, traced.rgb, traced.a);
+
+    let color = mix(s.color, reflection, s.fresnel) + s.specular;
+    return finish(in, color, max(alpha, s.fresnel));
+}
@@ -0,0 +1,383 @@
+pub const MAX_CASCADES: usize = 4;
+
+pub struct ShadowSettings {
+    pub enabled: bool,
+    pub cascades: usize,
+    pub resolution: u32,
+    pub distance: f32,
+    // Blend between logarithmic (1.0) and uniform (0.0) cascade splits.
+    pub split_lambda: f32
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        let (cascades, resolution) = if cfg!(target_arch = "wasm32") { (2, 1024) } else { (4, 2048) };
+
+        Self {
+            enabled: true,
+            cascades,
+            resolution,
+            distance: 96.0,
+            split_lambda: 0.75
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct ShadowUniform {
+    light_view_proj_mats: [[[f32; 4]; 4]; MAX_CASCADES],
+    normal_offsets: [f32; 4],
+    params: [f32; 4]
+}
+
+struct Cascade {
+    view: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+pub struct ShadowRenderer {
+    pipeline: wgpu::RenderPipeline,
+    cascade_layout: wgpu::BindGroupLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+    cascades: Vec<Cascade>,
+    bind_group: wgpu::BindGroup,
+    resolution: u32,
+    active: bool
+}
+
+impl ShadowRenderer {
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    const CASTER_MARGIN: f32 = 64.0;
+
+    pub fn new(device: &wgpu::Device, settings: &ShadowSettings) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[shadow] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/face.wgsl"),
+                include_str!("shaders/shadow.wgsl")
+            ).into())
+        });
+
+        let cascade_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[shadow] Cascade Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[shadow] Render Pipeline Layout"),
+            bind_group_layouts: &[&cascade_layout],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[shadow] Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[crate::terrain::Scene::buffer_layout::<0>()]
+            },
+            fragment: None,
+            primitive: Default::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Self::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: Default::default(),
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0
+                }
+            }),
+            multisample: Default::default(),
+            multiview: None
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[shadow] Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        sample_type: wgpu::TextureSampleType::Depth
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
+                }
+            ]
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("[shadow] Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[shadow] Uniform Buffer"),
+            size: std::mem::size_of::<ShadowUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let resolution = settings.resolution.min(device.limits().max_texture_dimension_2d);
+        let (cascades, bind_group) = Self::create_cascades(
+            device,
+            &cascade_layout,
+            &bind_group_layout,
+            &sampler,
+            &buffer,
+            settings.cascades.clamp(1, MAX_CASCADES),
+            resolution
+        );
+
+        Self {
+            pipeline,
+            cascade_layout,
+            bind_group_layout,
+            sampler,
+            buffer,
+            cascades,
+            bind_group,
+            resolution,
+            active: false
+        }
+    }
+
+    fn create_cascades(
+        device: &wgpu::Device,
+        cascade_layout: &wgpu::BindGroupLayout,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        buffer: &wgpu::Buffer,
+        count: usize,
+        resolution: u32
+    ) -> (Vec<Cascade>, wgpu::BindGroup) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[shadow] Shadow Map"),
+            size: wgpu::Extent3d {
+                width: resolution,
+                height: resolution,
+                // The GL backend cannot view a single-layer texture as an array.
+                depth_or_array_layers: count.max(2) as u32
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[]
+        });
+
+        let cascades = (0..count).map(|i| {
+            let view = texture.create_view(&wgpu::TextureViewDescriptor {
+                label: Some(&format!("[shadow] Cascade View {i}")),
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_array_layer: i as u32,
+                array_layer_count: Some(1),
+                ..Default::default()
+            });
+
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(&format!("[shadow] Cascade Buffer {i}")),
+                size: std::mem::size_of::<[[f32; 4]; 4]>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(&format!("[shadow] Cascade Bind Group {i}")),
+                layout: cascade_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding()
+                }]
+            });
+
+            Cascade { view, buffer, bind_group }
+        }).collect();
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("[shadow] Shadow Map View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[shadow] Bind Group"),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding()
+                }
+            ]
+        });
+
+        (cascades, bind_group)
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, camera: &crate::camera::Camera, projection: &crate::camera::Projection, sun_dir: cgmath::Vector3<f32>, settings: &ShadowSettings) {
+        let count = settings.cascades.clamp(1, MAX_CASCADES);
+        let resolution = settings.resolution.min(device.limits().max_texture_dimension_2d);
+        if count != self.cascades.len() || resolution != self.resolution {
+            (self.cascades, self.bind_group) = Self::create_cascades(
+                device,
+                &self.cascade_layout,
+                &self.bind_group_layout,
+                &self.sampler,
+                &self.buffer,
+                count,
+                resolution
+            );
+            self.resolution = resolution;
+        }
+
+        self.active = settings.enabled && sun_dir.y > 0.0;
+
+        // The logarithmic split needs a positive near plane, which orthographic views may not have.
+        let near = projection.near.max(crate::camera::Camera::Z_NEAR);
+        let far = settings.distance.min(projection.far);
+        let split = |i: usize| {
+            let t = i as f32 / count as f32;
+            let log = near * (far / near).powf(t);
+            let uniform = near + (far - near) * t;
+            settings.split_lambda * log + (1.0 - settings.split_lambda) * uniform
+        };
+
+        let mut uniform = ShadowUniform {
+            light_view_proj_mats: [[[0.0; 4]; 4]; MAX_CASCADES],
+            normal_offsets: [0.0; 4],
+            params: [count as f32, 1.0 / resolution as f32, if self.active { 1.0 } else { 0.0 }, 0.0]
+        };
+
+        for (i, cascade) in self.cascades.iter().enumerate() {
+            let (mat, texel) = Self::fit_cascade(camera, projection, sun_dir, split(i), split(i + 1), resolution);
+            let mat: [[f32; 4]; 4] = mat.into();
+            queue.write_buffer(&cascade.buffer, 0, bytemuck::cast_slice(&[mat]));
+            uniform.light_view_proj_mats[i] = mat;
+            uniform.normal_offsets[i] = texel * 1.5;
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    // Fits an orthographic sun view around the bounding sphere of the camera frustum slice,
+    // snapped to whole shadow map texels so that shadows do not shimmer as the camera moves.
+    fn fit_cascade(camera: &crate::camera::Camera, projection: &crate::camera::Projection, sun_dir: cgmath::Vector3<f32>, near: f32, far: f32, resolution: u32) -> (cgmath::Matrix4<f32>, f32) {
+        use cgmath::{EuclideanSpace, InnerSpace, Transform};
+
+        let (forward, right, up) = (camera.forward(), camera.right(), camera.up());
+
+        let corners: Vec<_> = [near, far].iter().flat_map(|&d| {
+            let (half_width, half_height) = projection.half_extent(camera.fovy, camera.aspect, d);
+            [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)].map(|(x, y)| {
+                camera.pos + forward * d + right * (x * half_width) + up * (y * half_height)
+            })
+        }).collect();
+
+        let center = cgmath::Point3::centroid(&corners);
+        let radius = corners.iter()
+            .map(|corner| (corner - center).magnitude())
+            .fold(0.0, f32::max);
+        let radius = (radius * 16.0).ceil() / 16.0;
+
+        let light_up = if sun_dir.y.abs() > 0.99 { cgmath::Vector3::unit_z() } else { cgmath::Vector3::unit_y() };
+        let view_mat = cgmath::Matrix4::look_to_rh(cgmath::Point3::origin(), -sun_dir, light_up);
+
+        let texel = radius * 2.0 / resolution as f32;
+        let center = view_mat.transform_point(center);
+        let x = (center.x / texel).floor() * texel;
+        let y = (center.y / texel).floor() * texel;
+
+        let proj_mat = cgmath::ortho(
+            x - radius,
+            x + radius,
+            y - radius,
+            y + radius,
+            -center.z - radius - Self::CASTER_MARGIN,
+            -center.z + radius
+        );
+
+        (crate::camera::Camera::OPENGL_TO_WGPU_MATRIX * proj_mat * view_mat, texel)
+    }
+
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, scene: &crate::terrain::Scene) {
+        if !self.active { return; }
+
+        for (i, cascade) in self.cascades.iter().enumerate() {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(&format!("[shadow] Render Pass Cascade {i}")),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &cascade.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store
+                    }),
+                    stencil_ops: None
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None
+            });
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &cascade.bind_group, &[]);
+            scene.draw(&mut rpass);
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
@@ -0,0 +1,150 @@
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct SkyUniform {
+    inv_view_proj_mat: [[f32; 4]; 4],
+    // Two depths along the view rays, the second farther away.
+    depths: [f32; 4]
+}
+
+impl SkyUniform {
+    pub fn new(camera: &crate::camera::Camera, projection: &crate::camera::Projection) -> Self {
+        let inv_view_proj_mat = projection.get_inv_view_proj_mat(camera);
+
+        Self {
+            inv_view_proj_mat: inv_view_proj_mat.into(),
+            depths: if projection.reversed_z { [0.75, 0.25, 0.0, 0.0] } else { [0.25, 0.75, 0.0, 0.0] }
+        }
+    }
+}
+
+pub struct SkyRenderer {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+impl SkyRenderer {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat, sample_count: u32, environment_layout: &wgpu::BindGroupLayout) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[sky] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/environment.wgsl"),
+                include_str!("shaders/sky.wgsl")
+            ).into())
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[sky] Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[sky] Render Pipeline Layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                environment_layout
+            ],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, format, sample_count);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[sky] Uniform Buffer"),
+            size: std::mem::size_of::<SkyUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[sky] Uniform Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding()
+            }]
+        });
+
+        Self {
+            pipeline,
+            pipeline_layout,
+            shader,
+            format,
+            buffer,
+            bind_group
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        sample_count: u32
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[sky] Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())]
+            }),
+            primitive: Default::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None
+        })
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, sample_count);
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue, camera: &crate::camera::Camera, projection: &crate::camera::Projection) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[SkyUniform::new(camera, projection)]));
+    }
+
+    // Clears `view` and fills it with the sky, so this should be the first pass of a frame.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView, environment: &crate::environment::Environment) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("[sky] Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store
+                }
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, environment.bind_group(), &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -29,52 +29,176 @@ impl BlockModel {
     }
 }
 
+pub const MAX_LIGHT: u8 = 15;
+
 pub trait WorldInterface {
     fn get_block(&self, coord: &IntCoord) -> &BlockModel;
     fn is_updated(&self, coord: &IntCoord) -> bool;
     fn get_updated_block_coords(&self) -> &[IntCoord];
+
+    // Baked sky light in 0..=MAX_LIGHT. Worlds without lighting are treated as fully exposed,
+    // so the day-night cycle then acts as a global brightness factor.
+    fn get_sky_light(&self, _coord: &IntCoord) -> Option<u8> {
+        None
+    }
+
+    // Water blocks should have no faces of their own, their surface is built separately
+    // and drawn by `water::WaterRenderer`.
+    fn is_water(&self, _coord: &IntCoord) -> bool {
+        false
+    }
+
+    // Light given off by the block in 0..=MAX_LIGHT. Its faces are drawn brighter than white,
+    // which only shows, and blooms, when rendering into an HDR target.
+    fn get_emission(&self, _coord: &IntCoord) -> u8 {
+        0
+    }
+}
+
+pub struct RaycastHit {
+    pub coord: IntCoord,
+    pub distance: f32,
+    // The face the ray entered through, `None` if it started inside the block.
+    pub face: Option<Direction>
+}
+
+// Walks the blocks along `ray` up to `max_distance`, returning the first one with any faces.
+// Water has none, so rays pass through it.
+pub fn raycast(world: &impl WorldInterface, ray: &crate::camera::Ray, max_distance: f32) -> Option<RaycastHit> {
+    let origin: [f32; 3] = ray.origin.into();
+    let dir: [f32; 3] = ray.dir.into();
+
+    let mut coord = origin.map(|v| v.floor() as i32);
+    let step = dir.map(|v| if v > 0.0 { 1 } else { -1 });
+    // Distance along the ray between crossings of each axis, and to the next crossing.
+    let delta = dir.map(|v| if v != 0.0 { 1.0 / v.abs() } else { f32::INFINITY });
+    // Axes the ray runs parallel to are never crossed, even from right on an edge, where the
+    // distance to it would be 0 * inf.
+    let mut next: [f32; 3] = std::array::from_fn(|i| {
+        if dir[i] == 0.0 { return f32::INFINITY; }
+        let to_edge = if dir[i] > 0.0 { coord[i] as f32 + 1.0 - origin[i] } else { origin[i] - coord[i] as f32 };
+        to_edge * delta[i]
+    });
+
+    let mut distance = 0.0;
+    let mut face = None;
+    loop {
+        let hit = IntCoord(coord);
+        if world.get_block(&hit).faces.iter().any(Option::is_some) {
+            return Some(RaycastHit { coord: hit, distance, face });
+        }
+
+        let axis = (0..3).fold(0, |min, i| if next[i] < next[min] { i } else { min });
+        distance = next[axis];
+        if distance > max_distance { return None; }
+
+        coord[axis] += step[axis];
+        next[axis] += delta[axis];
+        // Moving towards positive coordinates enters through the negative face.
+        face = Some(Direction(axis as u8 * 2 + (step[axis] < 0) as u8));
+    }
 }
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
-struct Face {
+pub(crate) struct Face {
     coord: [i32; 3],
     direction: u32,
-    texture: u32
+    // For water faces, 1 if the block is at the surface and 0 otherwise.
+    texture: u32,
+    sky_light: u32,
+    emission: u32
 }
 
-pub struct Scene {
+struct Chunk {
     faces: Vec<Face>,
-    buffer: crate::utils::DynamicBuffer
+    mesh: crate::arena::MeshId
+}
+
+// Faces are grouped by the chunk of `CHUNK_SIZE`³ blocks they belong to, so that an update
+// only uploads the chunks around it again. Chunks are meshes in one arena.
+pub struct Scene {
+    chunks: std::collections::HashMap<[i32; 3], Chunk>,
+    arena: crate::arena::MeshArena,
+    revision: u64,
+    mesher: Option<crate::meshing::GpuMesher>,
+    water_faces: Vec<Face>,
+    water_buffer: crate::utils::DynamicBuffer
 }
 
 impl Scene {
+    pub const CHUNK_SIZE: i32 = 16;
+    // Tiles along each side of the block texture atlas, see `terrain.wgsl`.
+    pub const ATLAS_TILES: u32 = 16;
+    // Faces moved per `compact` call.
+    const COMPACT_BUDGET: u32 = 16384;
+    // Updated blocks from which `GpuMesher` takes over, a chunk's worth.
+    const GPU_MESHING_MIN_BLOCKS: usize = (Self::CHUNK_SIZE * Self::CHUNK_SIZE * Self::CHUNK_SIZE) as usize;
+
     pub fn new(device: &wgpu::Device) -> Self {
         Self {
-            faces: vec![],
-            buffer: crate::utils::DynamicBuffer::new(
+            chunks: std::collections::HashMap::new(),
+            arena: crate::arena::MeshArena::new(
                 device,
-                "[terrain] Face Instance Buffer".into(),
+                "[terrain] Face Arena".into(),
+                std::mem::size_of::<Face>() as u32,
+                wgpu::BufferUsages::VERTEX
+            ),
+            revision: 0,
+            mesher: None,
+            water_faces: vec![],
+            water_buffer: crate::utils::DynamicBuffer::new(
+                device,
+                "[terrain] Water Face Instance Buffer".into(),
                 512,
                 wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST
             )
         }
     }
 
-    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &impl WorldInterface) {
-        let reserved = self.faces.drain(..).filter_map(|face| {
-            let coord = IntCoord(face.coord);
-            if world.is_updated(&coord) {
+    pub fn chunk_coord(coord: &IntCoord) -> [i32; 3] {
+        coord.0.map(|v| v.div_euclid(Self::CHUNK_SIZE))
+    }
+
+    fn water_faces<'a>(world: &'a impl WorldInterface, coord: &'a IntCoord) -> impl Iterator<Item = Face> + 'a {
+        let surface = !world.is_water(&coord.next(Direction(3)));
+
+        (0..6).filter_map(move |direction| {
+            let dir = Direction(direction as u8);
+            let facing_coord = coord.next(dir);
+            if world.is_water(&facing_coord) || world.get_block(&facing_coord).face(dir.opposite()).is_some() {
                 return None;
             }
 
-            let facing_coord = coord.next(Direction(face.direction as u8));
-            if world.is_updated(&facing_coord) {
-                None
-            } else {
-                Some(face)
-            }
-        });
+            Some(Face {
+                coord: coord.0,
+                direction,
+                texture: surface as u32,
+                sky_light: world.get_sky_light(&facing_coord).unwrap_or(MAX_LIGHT).into(),
+                emission: 0
+            })
+        })
+    }
+
+    // Keeps the faces of the chunks in `faces` that no update touched and adds the faces of the
+    // updated blocks. The reference for `meshing::GpuMesher`.
+    fn update_faces(
+        chunks: &std::collections::HashMap<[i32; 3], Chunk>,
+        world: &impl WorldInterface,
+        faces: &mut std::collections::HashMap<[i32; 3], Vec<Face>>
+    ) {
+        for (chunk_coord, chunk_faces) in faces.iter_mut() {
+            let Some(chunk) = chunks.get(chunk_coord) else { continue; };
+            chunk_faces.extend(chunk.faces.iter().copied().filter(|face| {
+                let coord = IntCoord(face.coord);
+                if world.is_updated(&coord) {
+                    return false;
+                }
+
+                let facing_coord = coord.next(Direction(face.direction as u8));
+                !world.is_updated(&facing_coord)
+            }));
+        }
 
         let added = world.get_updated_block_coords().iter().flat_map(|coord| {
             let block = world.get_block(coord);
@@ -84,6 +208,11 @@ impl Scene {
                 let oppo_dir = dir.opposite();
 
                 let facing_coord = coord.next(dir);
+                // An updated neighbour adds the face between them from its side instead.
+                if direction & 1 == 0 && world.is_updated(&facing_coord) {
+                    return None;
+                }
+
                 let facing_block = world.get_block(&facing_coord);
 
                 let face = block.face(dir);
@@ -95,32 +224,148 @@ impl Scene {
                         None => Some(Face {
                             coord: coord.0,
                             direction,
-                            texture: texture.get().into()
+                            texture: texture.get().into(),
+                            sky_light: world.get_sky_light(&facing_coord).unwrap_or(MAX_LIGHT).into(),
+                            emission: world.get_emission(coord).into()
                         })
                     }
-                    None => match facing_face {
-                        Some(texture) => Some(Face {
-                            coord: facing_coord.0,
-                            direction: oppo_dir.0.into(),
-                            texture: texture.get().into()
-                        }),
-                        None => None
-                    }
+                    None => facing_face.map(|texture| Face {
+                        coord: facing_coord.0,
+                        direction: oppo_dir.0.into(),
+                        texture: texture.get().into(),
+                        sky_light: world.get_sky_light(coord).unwrap_or(MAX_LIGHT).into(),
+                        emission: world.get_emission(&facing_coord).into()
+                    })
                 }
             })
         });
 
-        self.faces = reserved.chain(added).collect();
+        for face in added {
+            faces.get_mut(&Self::chunk_coord(&IntCoord(face.coord))).unwrap().push(face);
+        }
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, world: &impl WorldInterface) {
+        // Faces of a block depend on its neighbours, which may be in the chunks next to it.
+        let mut faces: std::collections::HashMap<_, _> = world.get_updated_block_coords().iter()
+            .flat_map(|coord| std::iter::once(Self::chunk_coord(coord))
+                .chain((0..6).map(|direction| Self::chunk_coord(&coord.next(Direction(direction))))))
+            .map(|chunk_coord| (chunk_coord, vec![]))
+            .collect();
 
-        self.buffer.update(device, queue, bytemuck::cast_slice(&self.faces));
+        match &mut self.mesher {
+            // Builds the chunks around the updates whole, rather than only the faces of the
+            // updated blocks, so blocks that were never reported as updated get faces too.
+            // That reads each block of the chunks once where the CPU path reads the neighbours
+            // of every updated block, but waits for the GPU, so only bulk updates such as
+            // loading or generating a world go this way and edits stay on the CPU.
+            Some(mesher) if world.get_updated_block_coords().len() >= Self::GPU_MESHING_MIN_BLOCKS => {
+                let chunk_coords: Vec<_> = faces.keys().copied().collect();
+                for face in mesher.mesh(device, queue, world, &chunk_coords) {
+                    faces.get_mut(&Self::chunk_coord(&IntCoord(face.coord))).unwrap().push(face);
+                }
+            }
+            _ => Self::update_faces(&self.chunks, world, &mut faces)
+        }
+
+        self.revision += 1;
+        for (chunk_coord, chunk_faces) in faces {
+            if chunk_faces.is_empty() {
+                if let Some(chunk) = self.chunks.remove(&chunk_coord) {
+                    self.arena.remove(device, queue, chunk.mesh);
+                }
+            } else if let Some(chunk) = self.chunks.get_mut(&chunk_coord) {
+                self.arena.update(device, queue, chunk.mesh, bytemuck::cast_slice(&chunk_faces));
+                chunk.faces = chunk_faces;
+            } else {
+                let mesh = self.arena.insert(device, queue, bytemuck::cast_slice(&chunk_faces));
+                self.chunks.insert(chunk_coord, Chunk { faces: chunk_faces, mesh });
+            }
+        }
+
+        // A water face depends on its neighbours and on the block above, which decides whether
+        // it is lowered to the surface level, so every water block next to a change is rebuilt.
+        let mut affected = std::collections::HashSet::new();
+        for coord in world.get_updated_block_coords() {
+            affected.insert(coord.0);
+            affected.extend((0..6).map(|direction| coord.next(Direction(direction)).0));
+        }
+
+        self.water_faces.retain(|face| !affected.contains(&face.coord));
+        for coord in affected {
+            let coord = IntCoord(coord);
+            if world.is_water(&coord) {
+                self.water_faces.extend(Self::water_faces(world, &coord));
+            }
+        }
+
+        self.water_buffer.update(device, queue, bytemuck::cast_slice(&self.water_faces));
+    }
+
+    // Builds the faces of bulk updates in a compute pass instead, if the device can. Returns
+    // whether it does.
+    pub fn set_gpu_meshing(&mut self, device: &wgpu::Device, enabled: bool) -> bool {
+        self.mesher = if enabled { crate::meshing::GpuMesher::new(device) } else { None };
+        self.mesher.is_some()
+    }
+
+    pub fn gpu_meshing(&self) -> bool {
+        self.mesher.is_some()
+    }
+
+    // Moves chunk meshes together after updates left gaps between them, a bit per call.
+    pub fn compact(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.arena.fragmented() > 0 {
+            self.arena.compact(device, queue, Self::COMPACT_BUDGET);
+            self.revision += 1;
+        }
+    }
+
+    // Changes whenever the chunks or their ranges in the arena do.
+    pub fn revision(&self) -> u64 {
+        self.revision
     }
 
     pub fn len(&self) -> usize {
-        self.faces.len()
+        self.chunks.values().map(|chunk| chunk.faces.len()).sum()
     }
 
-    pub fn buffer(&self) -> &wgpu::Buffer {
-        self.buffer.buffer()
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    // In no particular order.
+    #[cfg(test)]
+    pub(crate) fn faces(&self) -> impl Iterator<Item = &Face> {
+        self.chunks.values().flat_map(|chunk| &chunk.faces)
+    }
+
+    pub fn arena(&self) -> &crate::arena::MeshArena {
+        &self.arena
+    }
+
+    // The face ranges of the chunks in the arena, by chunk coordinate.
+    pub fn chunks(&self) -> impl Iterator<Item = ([i32; 3], crate::arena::MeshRange)> + '_ {
+        self.chunks.iter().map(|(chunk_coord, chunk)| (*chunk_coord, self.arena.get(chunk.mesh)))
+    }
+
+    // Draws the faces as instances of 6 vertices, a pipeline with `buffer_layout` at slot 0
+    // has to be set.
+    pub(crate) fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        for page in 0..self.arena.page_count() {
+            rpass.set_vertex_buffer(0, self.arena.buffer(page).slice(..));
+            for (_, range) in self.chunks().filter(|(_, range)| range.page == page) {
+                rpass.draw(0..6, range.elements());
+            }
+        }
+    }
+
+    pub fn water_len(&self) -> usize {
+        self.water_faces.len()
+    }
+
+    pub fn water_buffer(&self) -> &wgpu::Buffer {
+        self.water_buffer.buffer()
     }
 
     pub fn buffer_layout<const N: u32>() -> wgpu::VertexBufferLayout<'static> {
@@ -142,6 +387,16 @@ impl Scene {
                     offset: 16,
                     shader_location: N + 2,
                     format: wgpu::VertexFormat::Uint32
+                },
+                wgpu::VertexAttribute {
+                    offset: 20,
+                    shader_location: N + 3,
+                    format: wgpu::VertexFormat::Uint32
+                },
+                wgpu::VertexAttribute {
+                    offset: 24,
+                    shader_location: N + 4,
+                    format: wgpu::VertexFormat::Uint32
                 }
             ]
         }
@@ -166,12 +421,38 @@ impl Scene {
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
                     count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    },
+                    count: None
                 }
             ]
         })
     }
 
-    pub fn create_texture(device: &wgpu::Device, queue: &wgpu::Queue, layout: &wgpu::BindGroupLayout, width: u32, height: u32, data: &[u8]) -> wgpu::BindGroup {
+    // Levels down to a texel per tile, as far as the tiles of each level stay whole, so that no
+    // level mixes neighbouring tiles.
+    pub fn atlas_mip_level_count(width: u32, height: u32) -> u32 {
+        (width | height).trailing_zeros().saturating_sub(Self::ATLAS_TILES.trailing_zeros()) + 1
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmap_generator: &mut crate::mipmap::MipmapGenerator,
+        layout: &wgpu::BindGroupLayout,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        settings: &SamplerSettings
+    ) -> TerrainTexture {
         let size = wgpu::Extent3d {
             width,
             height,
@@ -181,13 +462,11 @@ impl Scene {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some("[terrain] Texture"),
             size,
-            mip_level_count: 5,
+            mip_level_count: Self::atlas_mip_level_count(width, height),
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING |
-                wgpu::TextureUsages::COPY_DST |
-                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
             view_formats: &[]
         });
 
@@ -202,37 +481,262 @@ impl Scene {
             rows_per_image: Some(height)
         }, size);
 
-        crate::utils::generate_mipmaps(device, queue, &texture);
+        // Cutout tiles keep their colour at a distance.
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("[terrain] Mipmap Encoder")
+        });
+        mipmap_generator.generate(device, &mut encoder, &texture, &crate::mipmap::MipmapOptions {
+            alpha_weighted: true,
+            ..Default::default()
+        });
+        queue.submit(Some(encoder.finish()));
 
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
             label: Some("[terrain] Texture View"),
             ..Default::default()
         });
 
+        let bind_group = TerrainTexture::create_bind_group(device, layout, &view, texture.mip_level_count(), settings);
+        TerrainTexture {
+            view,
+            mip_level_count: texture.mip_level_count(),
+            settings: *settings,
+            bind_group
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SamplerSettings {
+    // Anisotropic filtering for 2 to 16, which also filters texels linearly as it requires.
+    pub anisotropy: u16,
+    pub mipmaps: bool,
+    // Added to the mip level, negative for sharper textures at a distance.
+    pub lod_bias: f32
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            anisotropy: 1,
+            mipmaps: true,
+            lod_bias: 0.0
+        }
+    }
+}
+
+// The block texture atlas, bound with the layout of `Scene::texture_bind_group_layout`.
+pub struct TerrainTexture {
+    view: wgpu::TextureView,
+    mip_level_count: u32,
+    settings: SamplerSettings,
+    bind_group: wgpu::BindGroup
+}
+
+impl TerrainTexture {
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        view: &wgpu::TextureView,
+        mip_level_count: u32,
+        settings: &SamplerSettings
+    ) -> wgpu::BindGroup {
+        use wgpu::util::DeviceExt;
+
+        let anisotropy = settings.anisotropy.clamp(1, 16);
+        let filter = if anisotropy > 1 { wgpu::FilterMode::Linear } else { wgpu::FilterMode::Nearest };
+        let lod_max_clamp = if settings.mipmaps { (mip_level_count - 1) as f32 } else { 0.0 };
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("[terrain] Texture Sampler"),
             address_mode_u: wgpu::AddressMode::ClampToEdge,
             address_mode_v: wgpu::AddressMode::ClampToEdge,
             address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Nearest,
-            min_filter: wgpu::FilterMode::Nearest,
+            mag_filter: filter,
+            min_filter: filter,
             mipmap_filter: wgpu::FilterMode::Linear,
+            lod_max_clamp,
+            anisotropy_clamp: anisotropy,
             ..Default::default()
         });
 
+        // Samplers have no bias in WebGPU, the shader adds it. It also keeps linear filtering
+        // from reaching into neighbouring tiles of the atlas.
+        let linear = if filter == wgpu::FilterMode::Linear { 1.0 } else { 0.0 };
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("[terrain] Texture Params Buffer"),
+            contents: bytemuck::cast_slice(&[settings.lod_bias, linear, lod_max_clamp, 0.0]),
+            usage: wgpu::BufferUsages::UNIFORM
+        });
+
         device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("[terrain] Texture Bind Group"),
             layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&view)
+                    resource: wgpu::BindingResource::TextureView(view)
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&sampler)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: params_buffer.as_entire_binding()
                 }
             ]
         })
     }
+
+    // Rebuilds the bind group with a new sampler, if the settings changed.
+    pub fn set_sampler_settings(&mut self, device: &wgpu::Device, layout: &wgpu::BindGroupLayout, settings: &SamplerSettings) {
+        if *settings == self.settings { return; }
+
+        self.settings = *settings;
+        self.bind_group = Self::create_bind_group(device, layout, &self.view, self.mip_level_count, settings);
+    }
+
+    pub fn sampler_settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+}
+
+pub struct TerrainRenderer {
+    pipeline: wgpu::RenderPipeline,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    sample_count: u32,
+    reversed_z: bool,
+    culler: crate::cull::ChunkCuller
+}
+
+impl TerrainRenderer {
+    // Bind groups are set by the caller: 0 texture, 1 camera, 2 environment and 3 shadow.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        texture_layout: &wgpu::BindGroupLayout,
+        camera_layout: &wgpu::BindGroupLayout,
+        environment_layout: &wgpu::BindGroupLayout,
+        shadow_layout: &wgpu::BindGroupLayout
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[terrain] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/face.wgsl"),
+                include_str!("shaders/environment.wgsl"),
+                include_str!("shaders/terrain.wgsl")
+            ).into())
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[terrain] Render Pipeline Layout"),
+            bind_group_layouts: &[
+                texture_layout,
+                camera_layout,
+                environment_layout,
+                shadow_layout
+            ],
+            push_constant_ranges: &[]
+        });
+
+        let pipeline = Self::create_pipeline(device, &pipeline_layout, &shader, format, depth_format, sample_count, false);
+
+        Self {
+            pipeline,
+            pipeline_layout,
+            shader,
+            format,
+            depth_format,
+            sample_count,
+            reversed_z: false,
+            culler: crate::cull::ChunkCuller::new(device)
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        reversed_z: bool
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[terrain] Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[Scene::buffer_layout::<0>()]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: if reversed_z { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less },
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                mask: !0,
+                alpha_to_coverage_enabled: false
+            },
+            multiview: None
+        })
+    }
+
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, self.depth_format, sample_count, self.reversed_z);
+    }
+
+    // Has to match `camera::Projection::reversed_z` of the rendered camera.
+    pub fn set_reversed_z(&mut self, device: &wgpu::Device, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+        self.pipeline = Self::create_pipeline(device, &self.pipeline_layout, &self.shader, self.format, self.depth_format, self.sample_count, reversed_z);
+    }
+
+    // Culls the chunks of `scene` against the camera, call every frame before `cull`.
+    pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene, camera: &crate::camera::Camera, projection: &crate::camera::Projection) {
+        self.culler.update(device, queue, scene, camera, projection);
+    }
+
+    // Records the culling compute pass, if any, before the render pass.
+    pub fn cull(&self, encoder: &mut wgpu::CommandEncoder) {
+        self.culler.cull(encoder);
+    }
+
+    pub fn render<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, scene: &'a Scene) {
+        rpass.set_pipeline(&self.pipeline);
+        self.culler.draw(rpass, scene);
+    }
 }
@@ -1,6 +1,18 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShrinkPolicy {
+    Never,
+    // Halves the buffer while less than a quarter of it is used, down to its initial size. The
+    // gap keeps a buffer around the threshold from being recreated on every update.
+    BelowQuarter
+}
+
+// A buffer that grows in powers of two as more data is written to it. `COPY_SRC` and
+// `COPY_DST` are added to its usage, so that it can be resized without losing its contents.
 pub struct DynamicBuffer {
     label: String,
-    buffer: wgpu::Buffer
+    buffer: wgpu::Buffer,
+    initial_size: wgpu::BufferAddress,
+    shrink_policy: ShrinkPolicy
 }
 
 impl DynamicBuffer {
@@ -8,124 +20,103 @@ impl DynamicBuffer {
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some(&label),
             size: initial_size,
-            usage,
+            usage: usage | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false
         });
 
-        Self { label, buffer }
+        Self {
+            label,
+            buffer,
+            initial_size,
+            shrink_policy: ShrinkPolicy::Never
+        }
+    }
+
+    pub fn set_shrink_policy(&mut self, shrink_policy: ShrinkPolicy) {
+        self.shrink_policy = shrink_policy;
     }
 
+    // Replaces the contents with `data`, which the buffer may shrink to.
     pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) {
         let data_size = data.len() as wgpu::BufferAddress;
-        let old_size = self.buffer.size();
-        eprintln!("{} / {}", data_size, old_size);
-        if data_size > old_size {
-            let mut new_size = old_size;
-            while new_size < data_size { new_size *= 2; }
-            eprintln!("{} -> {}", old_size, new_size);
-            self.buffer = device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some(&self.label),
-                size: new_size,
-                usage: self.buffer.usage(),
-                mapped_at_creation: false
-            });
+        if data_size > self.buffer.size() {
+            self.resize(device, queue, Self::grown_size(self.buffer.size(), data_size), 0);
+        } else if let Some(size) = self.shrunk_size(data_size) {
+            self.resize(device, queue, size, 0);
         }
 
         queue.write_buffer(&self.buffer, 0, data);
     }
 
-    pub fn buffer(&self) -> &wgpu::Buffer {
-        &self.buffer
+    // Writes `data` at `offset`, growing the buffer if needed. Everything else is kept.
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, offset: wgpu::BufferAddress, data: &[u8]) {
+        let end = offset + data.len() as wgpu::BufferAddress;
+        if end > self.buffer.size() {
+            let size = self.buffer.size();
+            self.resize(device, queue, Self::grown_size(size, end), size);
+        }
+
+        queue.write_buffer(&self.buffer, offset, data);
     }
-}
 
-pub fn generate_mipmaps(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) {
-    let mip_cnt = texture.mip_level_count();
-
-    let shader = device.create_shader_module(wgpu::include_wgsl!("shaders/mipmap.wgsl"));
-
-    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-        label: Some("[mipmap] Render Pipeline"),
-        layout: None,
-        vertex: wgpu::VertexState {
-            module: &shader,
-            entry_point: "vs_main",
-            buffers: &[]
-        },
-        fragment: Some(wgpu::FragmentState {
-            module: &shader,
-            entry_point: "fs_main",
-            targets: &[Some(texture.format().into())]
-        }),
-        primitive: Default::default(),
-        depth_stencil: None,
-        multisample: Default::default(),
-        multiview: None
-    });
-
-    let bind_group_layout = pipeline.get_bind_group_layout(0);
-
-    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        label: Some("[mipmap] Sampler"),
-        address_mode_u: wgpu::AddressMode::ClampToEdge,
-        address_mode_v: wgpu::AddressMode::ClampToEdge,
-        address_mode_w: wgpu::AddressMode::ClampToEdge,
-        mag_filter: wgpu::FilterMode::Linear,
-        min_filter: wgpu::FilterMode::Linear,
-        mipmap_filter: wgpu::FilterMode::Nearest,
-        ..Default::default()
-    });
-
-    let views: Vec<_> = (0..mip_cnt).map(|level| texture.create_view(&wgpu::TextureViewDescriptor {
-        label: Some(&format!("[mipmap] View Level {level}")),
-        format: None,
-        dimension: None,
-        aspect: wgpu::TextureAspect::All,
-        base_mip_level: level,
-        mip_level_count: Some(1),
-        base_array_layer: 0,
-        array_layer_count: None
-    })).collect();
-
-    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-        label: Some("[mipmap] Render Encoder")
-    });
-
-    for level in 1..mip_cnt as usize {
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some(&format!("[mipmap] Texture Bind Group Level {level}")),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&views[level - 1])
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler)
-                }
-            ]
-        });
+    // Grows the buffer to at least `size` bytes, keeping its contents.
+    pub fn reserve(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: wgpu::BufferAddress) {
+        if size > self.buffer.size() {
+            let old_size = self.buffer.size();
+            self.resize(device, queue, Self::grown_size(old_size, size), old_size);
+        }
+    }
 
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some(&format!("[mipmap] Render Pass Level {level}")),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &views[level],
-                resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                    store: wgpu::StoreOp::Store
-                }
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None
+    // Applies the shrink policy for users of `write`, given that only the first `used` bytes
+    // are needed. Those are kept.
+    pub fn shrink(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, used: wgpu::BufferAddress) {
+        if let Some(size) = self.shrunk_size(used) {
+            self.resize(device, queue, size, used);
+        }
+    }
+
+    fn grown_size(size: wgpu::BufferAddress, needed: wgpu::BufferAddress) -> wgpu::BufferAddress {
+        let mut new_size = size.max(1);
+        while new_size < needed { new_size *= 2; }
+        new_size
+    }
+
+    fn shrunk_size(&self, used: wgpu::BufferAddress) -> Option<wgpu::BufferAddress> {
+        match self.shrink_policy {
+            ShrinkPolicy::Never => None,
+            ShrinkPolicy::BelowQuarter => {
+                let mut new_size = self.buffer.size();
+                while new_size / 2 >= self.initial_size && used < new_size / 4 { new_size /= 2; }
+                (new_size != self.buffer.size()).then_some(new_size)
+            }
+        }
+    }
+
+    // Replaces the buffer with one of `size` bytes, copying over the first `keep`.
+    fn resize(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, size: wgpu::BufferAddress, keep: wgpu::BufferAddress) {
+        log::debug!("{}: {} -> {} bytes", self.label, self.buffer.size(), size);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&self.label),
+            size,
+            usage: self.buffer.usage(),
+            mapped_at_creation: false
         });
 
-        render_pass.set_pipeline(&pipeline);
-        render_pass.set_bind_group(0, &bind_group, &[]);
-        render_pass.draw(0..3, 0..1);
+        // Copies have to be multiples of 4 bytes, which the sizes are as long as the initial one is.
+        let keep = keep.min(size) & !(wgpu::COPY_BUFFER_ALIGNMENT - 1);
+        if keep > 0 {
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("[utils] Buffer Resize Encoder")
+            });
+            encoder.copy_buffer_to_buffer(&self.buffer, 0, &buffer, 0, keep);
+            queue.submit(Some(encoder.finish()));
+        }
+
+        self.buffer = buffer;
     }
 
-    queue.submit(Some(encoder.finish()));
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
 }
@@ -0,0 +1,375 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WaterQuality {
+    // Sky reflection only, depth tested like any other surface. Works everywhere.
+    Simple,
+    // Screen-space reflections of the frame and depth-based opacity. Needs a copy of the
+    // frame, see `WaterRenderer::set_targets`.
+    Reflective
+}
+
+pub struct WaterSettings {
+    pub quality: WaterQuality,
+    // Height of the surface within its block.
+    pub level: f32,
+    pub wave_height: f32,
+    pub wave_speed: f32,
+    pub color: [f32; 3],
+    // How quickly the water turns opaque with depth, per block.
+    pub absorption: f32
+}
+
+impl Default for WaterSettings {
+    fn default() -> Self {
+        Self {
+            quality: if cfg!(target_arch = "wasm32") {
+                WaterQuality::Simple
+            } else {
+                WaterQuality::Reflective
+            },
+            level: 0.875,
+            wave_height: 0.04,
+            wave_speed: 1.0,
+            color: [0.1, 0.3, 0.55],
+            absorption: 0.6
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+struct WaterUniform {
+    view_proj_mat: [[f32; 4]; 4],
+    inv_view_proj_mat: [[f32; 4]; 4],
+    camera_pos: [f32; 4],
+    color: [f32; 4],
+    params: [f32; 4]
+}
+
+struct Targets {
+    scene: wgpu::Texture,
+    bind_group: wgpu::BindGroup
+}
+
+pub struct WaterRenderer {
+    simple_pipeline: wgpu::RenderPipeline,
+    simple_pipeline_layout: wgpu::PipelineLayout,
+    reflective_pipeline: wgpu::RenderPipeline,
+    shader: wgpu::ShaderModule,
+    targets_layout: wgpu::BindGroupLayout,
+    targets: Option<Targets>,
+    format: wgpu::TextureFormat,
+    depth_format: wgpu::TextureFormat,
+    sample_count: u32,
+    reversed_z: bool,
+    quality: WaterQuality,
+    time: f32,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+impl WaterRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        depth_format: wgpu::TextureFormat,
+        sample_count: u32,
+        environment_layout: &wgpu::BindGroupLayout
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("[water] Shader"),
+            source: wgpu::ShaderSource::Wgsl(concat!(
+                include_str!("shaders/face.wgsl"),
+                include_str!("shaders/environment.wgsl"),
+                include_str!("shaders/water.wgsl")
+            ).into())
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[water] Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        });
+
+        let targets_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("[water] Targets Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    // Read as a plain float texture, as loading from depth textures is not
+                    // supported on GL.
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                }
+            ]
+        });
+
+        let simple_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[water] Simple Render Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout, environment_layout],
+            push_constant_ranges: &[]
+        });
+
+        let reflective_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("[water] Reflective Render Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout, environment_layout, &targets_layout],
+            push_constant_ranges: &[]
+        });
+
+        let simple_pipeline = Self::create_pipeline(
+            device,
+            &simple_pipeline_layout,
+            &shader,
+            format,
+            Some(depth_format),
+            sample_count,
+            false
+        );
+
+        // Tests against the depth buffer in the shader, since the pass also samples it. The depth
+        // buffer can only be read when it is single-sampled, so this is never multisampled.
+        let reflective_pipeline = Self::create_pipeline(device, &reflective_pipeline_layout, &shader, format, None, 1, false);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("[water] Uniform Buffer"),
+            size: std::mem::size_of::<WaterUniform>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[water] Uniform Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding()
+            }]
+        });
+
+        Self {
+            simple_pipeline,
+            simple_pipeline_layout,
+            reflective_pipeline,
+            shader,
+            targets_layout,
+            targets: None,
+            format,
+            depth_format,
+            sample_count,
+            reversed_z: false,
+            quality: WaterQuality::Simple,
+            time: 0.0,
+            buffer,
+            bind_group
+        }
+    }
+
+    // Uses the simple fragment shader when given a depth format, and the reflective one otherwise.
+    fn create_pipeline(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+        depth_format: Option<wgpu::TextureFormat>,
+        sample_count: u32,
+        reversed_z: bool
+    ) -> wgpu::RenderPipeline {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("[water] Render Pipeline"),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[crate::terrain::Scene::buffer_layout::<0>()]
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: if depth_format.is_some() { "fs_simple" } else { "fs_reflective" },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            // Visible from below as well, when the camera is underwater.
+            primitive: Default::default(),
+            depth_stencil: depth_format.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: false,
+                depth_compare: if reversed_z { wgpu::CompareFunction::Greater } else { wgpu::CompareFunction::Less },
+                stencil: Default::default(),
+                bias: Default::default()
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None
+        })
+    }
+
+    // Reflective mode is only available while this is 1.
+    pub fn set_sample_count(&mut self, device: &wgpu::Device, sample_count: u32) {
+        self.sample_count = sample_count;
+        self.simple_pipeline = Self::create_pipeline(
+            device,
+            &self.simple_pipeline_layout,
+            &self.shader,
+            self.format,
+            Some(self.depth_format),
+            sample_count,
+            self.reversed_z
+        );
+    }
+
+    // Has to match `camera::Projection::reversed_z` of the rendered camera. The reflective
+    // pipeline tests depth in the shader and follows the camera given to `update` instead.
+    pub fn set_reversed_z(&mut self, device: &wgpu::Device, reversed_z: bool) {
+        self.reversed_z = reversed_z;
+        self.simple_pipeline = Self::create_pipeline(
+            device,
+            &self.simple_pipeline_layout,
+            &self.shader,
+            self.format,
+            Some(self.depth_format),
+            self.sample_count,
+            reversed_z
+        );
+    }
+
+    // Enables `WaterQuality::Reflective`. `depth_view` is the frame's depth buffer, which needs
+    // `TEXTURE_BINDING` usage, and must be set again whenever it is recreated.
+    pub fn set_targets(&mut self, device: &wgpu::Device, depth_view: &wgpu::TextureView, width: u32, height: u32) {
+        let scene = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("[water] Scene Copy Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("[water] Targets Bind Group"),
+            layout: &self.targets_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&scene.create_view(&Default::default()))
+                }
+            ]
+        });
+
+        self.targets = Some(Targets { scene, bind_group });
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &crate::camera::Camera, projection: &crate::camera::Projection, secs: f32, settings: &WaterSettings) {
+        self.quality = settings.quality;
+        self.time += secs * settings.wave_speed;
+
+        let view_proj_mat = projection.get_view_proj_mat(camera);
+        let inv_view_proj_mat = projection.get_inv_view_proj_mat(camera);
+        let [r, g, b] = settings.color;
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[WaterUniform {
+            view_proj_mat: view_proj_mat.into(),
+            inv_view_proj_mat: inv_view_proj_mat.into(),
+            camera_pos: [camera.pos.x, camera.pos.y, camera.pos.z, 1.0],
+            color: [r, g, b, settings.absorption],
+            params: [self.time, settings.wave_height, settings.level, if projection.reversed_z { 1.0 } else { 0.0 }]
+        }]));
+    }
+
+    fn reflective(&self) -> bool {
+        self.quality == WaterQuality::Reflective && self.targets.is_some() && self.sample_count == 1
+    }
+
+    // Draws over an already rendered frame into `view`. In reflective mode `frame`, the texture
+    // behind `view`, is copied first, so it needs `COPY_SRC` usage and the size given to
+    // `set_targets`.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &wgpu::Texture,
+        view: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        environment: &crate::environment::Environment,
+        scene: &crate::terrain::Scene
+    ) {
+        if scene.water_len() == 0 { return; }
+
+        let targets = self.targets.as_ref().filter(|_| self.reflective());
+
+        if let Some(targets) = targets {
+            encoder.copy_texture_to_texture(frame.as_image_copy(), targets.scene.as_image_copy(), targets.scene.size());
+        }
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("[water] Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store
+                }
+            })],
+            depth_stencil_attachment: targets.is_none().then_some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store
+                }),
+                stencil_ops: None
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None
+        });
+
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(1, environment.bind_group(), &[]);
+
+        match targets {
+            Some(targets) => {
+                rpass.set_pipeline(&self.reflective_pipeline);
+                rpass.set_bind_group(2, &targets.bind_group, &[]);
+            }
+            None => rpass.set_pipeline(&self.simple_pipeline)
+        }
+
+        rpass.set_vertex_buffer(0, scene.water_buffer().slice(..));
+        rpass.draw(0..6, 0..scene.water_len() as u32);
+    }
+}
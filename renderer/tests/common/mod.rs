@@ -0,0 +1,82 @@
+// Fixtures shared by the tests that render or mesh worlds, also included by the unit tests of
+// `meshing` through a `#[path]` module, which is why the crate is named `renderer` there too.
+#![allow(dead_code)]
+
+use renderer::terrain::{BlockModel, IntCoord, WorldInterface, MAX_LIGHT};
+use std::collections::{HashMap, HashSet};
+
+// Blocks are indices into `models`, 0 or missing ones are empty. The blocks of the last `new` or
+// `set` are reported as updated, so a single `Scene::update` builds all faces.
+pub struct World {
+    models: Vec<BlockModel>,
+    blocks: HashMap<[i32; 3], usize>,
+    updated: Vec<IntCoord>,
+    updated_set: HashSet<[i32; 3]>,
+    lit: bool
+}
+
+impl World {
+    pub fn new(models: Vec<BlockModel>, blocks: impl IntoIterator<Item = ([i32; 3], usize)>) -> Self {
+        let mut world = Self {
+            models,
+            blocks: HashMap::new(),
+            updated: vec![],
+            updated_set: HashSet::new(),
+            lit: false
+        };
+        world.set(blocks);
+        world
+    }
+
+    // Sky light and emission that vary between blocks, instead of full sky light and none.
+    pub fn lit(mut self) -> Self {
+        self.lit = true;
+        self
+    }
+
+    pub fn set(&mut self, blocks: impl IntoIterator<Item = ([i32; 3], usize)>) {
+        self.updated.clear();
+        self.updated_set.clear();
+        for (coord, model) in blocks {
+            self.blocks.insert(coord, model);
+            if self.updated_set.insert(coord) {
+                self.updated.push(IntCoord(coord));
+            }
+        }
+    }
+}
+
+impl WorldInterface for World {
+    fn get_block(&self, coord: &IntCoord) -> &BlockModel {
+        &self.models[self.blocks.get(&coord.0).copied().unwrap_or(0)]
+    }
+
+    fn is_updated(&self, coord: &IntCoord) -> bool {
+        self.updated_set.contains(&coord.0)
+    }
+
+    fn get_updated_block_coords(&self) -> &[IntCoord] {
+        &self.updated
+    }
+
+    fn get_sky_light(&self, coord: &IntCoord) -> Option<u8> {
+        let [x, y, z] = coord.0;
+        (self.lit && y < 16).then(|| (x + 2 * z).rem_euclid(MAX_LIGHT as i32 + 1) as u8)
+    }
+
+    fn get_emission(&self, coord: &IntCoord) -> u8 {
+        let [x, y, z] = coord.0;
+        if self.lit { ((x ^ y ^ z) & 7) as u8 } else { 0 }
+    }
+}
+
+// On the fallback adapter, such as llvmpipe or WARP, so that results don't depend on the GPU.
+// Tests fail rather than pass without looking when there is none.
+pub fn device() -> (wgpu::Device, wgpu::Queue) {
+    let instance = wgpu::Instance::new(Default::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        force_fallback_adapter: true,
+        ..Default::default()
+    })).expect("the GPU tests need a fallback adapter");
+    pollster::block_on(adapter.request_device(&Default::default(), None)).unwrap()
+}
@@ -0,0 +1,176 @@
+// Renders fixed scenes with the fallback adapter and compares them against the reference images
+// in `tests/golden`. Run with `UPDATE_GOLDEN=1` to write the references instead, after checking
+// that a change in the output is intended.
+
+mod common;
+
+use common::World;
+use renderer::terrain::{BlockModel, TextureId};
+use std::sync::{Mutex, OnceLock};
+
+const WIDTH: u32 = 320;
+const HEIGHT: u32 = 240;
+
+// Largest difference of a channel before a pixel counts as different, and the share of pixels
+// allowed to differ, to absorb rounding between software rasterizers.
+const CHANNEL_TOLERANCE: u8 = 16;
+const PIXEL_TOLERANCE: f32 = 0.01;
+
+const ATLAS_SIZE: u32 = 256;
+const TILE_SIZE: u32 = 16;
+
+// Every tile has its own colour, a white square in its top left corner and a dark top edge, so
+// rotated and mirrored faces both show.
+fn atlas() -> Vec<u8> {
+    let mut data = Vec::with_capacity((ATLAS_SIZE * ATLAS_SIZE * 4) as usize);
+    for y in 0..ATLAS_SIZE {
+        for x in 0..ATLAS_SIZE {
+            let id = y / TILE_SIZE * (ATLAS_SIZE / TILE_SIZE) + x / TILE_SIZE;
+            let (u, v) = (x % TILE_SIZE, y % TILE_SIZE);
+            let color = if u < 5 && v < 5 {
+                [255, 255, 255]
+            } else if v < 2 {
+                [20, 20, 20]
+            } else {
+                [(id * 97 % 200 + 55) as u8, (id * 57 % 200 + 55) as u8, (id * 31 % 200 + 55) as u8]
+            };
+            data.extend(color);
+            data.push(255);
+        }
+    }
+    data
+}
+
+// Models 1 and 2 are plain, 3 has a texture per direction and `TILE + i` has the tile `i` of
+// the atlas on its +z face only.
+const TILE: usize = 4;
+
+fn models() -> Vec<BlockModel> {
+    let face = |i: usize| TextureId::new(i as u8);
+    let mut models = vec![
+        BlockModel { faces: [None; 6] },
+        BlockModel { faces: [face(1); 6] },
+        BlockModel { faces: [face(2); 6] },
+        BlockModel { faces: [face(3), face(4), face(5), face(6), face(7), face(8)] }
+    ];
+    models.extend((0..16).map(|i| BlockModel { faces: [None, None, None, None, None, face(i + 1)] }));
+    models
+}
+
+fn camera(pos: [f32; 3], target: [f32; 3]) -> renderer::camera::Camera {
+    let dir = [target[0] - pos[0], target[1] - pos[1], target[2] - pos[2]];
+    renderer::camera::Camera::new(
+        pos.into(),
+        cgmath::Rad(dir[2].atan2(dir[0])).into(),
+        cgmath::Rad(dir[1].atan2(dir[0].hypot(dir[2]))).into(),
+        cgmath::Deg(60.0),
+        WIDTH as f32 / HEIGHT as f32
+    )
+}
+
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    mipmap_generator: renderer::mipmap::MipmapGenerator
+}
+
+// Tests share one device and render one at a time.
+fn gpu() -> &'static Mutex<Gpu> {
+    static GPU: OnceLock<Mutex<Gpu>> = OnceLock::new();
+    GPU.get_or_init(|| {
+        let (device, queue) = common::device();
+        Mutex::new(Gpu { device, queue, mipmap_generator: Default::default() })
+    })
+}
+
+fn render(world: &World, camera: &renderer::camera::Camera) -> image::RgbaImage {
+    let mut gpu = gpu().lock().unwrap_or_else(|e| e.into_inner());
+    let Gpu { device, queue, mipmap_generator } = &mut *gpu;
+
+    let mut settings = renderer::headless::HeadlessSettings::default();
+    settings.shadow.enabled = false;
+    settings.cloud.enabled = false;
+    settings.post.bloom = false;
+    settings.post.fxaa = false;
+    settings.post.tone_mapping = false;
+
+    let mut renderer = renderer::headless::HeadlessRenderer::new(device, queue, mipmap_generator, ATLAS_SIZE, ATLAS_SIZE, &atlas());
+    let image = renderer.render(device, queue, world, camera, WIDTH, HEIGHT, &settings).unwrap();
+    image::RgbaImage::from_raw(image.width, image.height, image.data).unwrap()
+}
+
+fn compare(name: &str, actual: &image::RgbaImage) {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let reference_path = dir.join(format!("{name}.png"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(&dir).unwrap();
+        actual.save(&reference_path).unwrap();
+        return;
+    }
+
+    let reference = image::open(&reference_path)
+        .unwrap_or_else(|e| panic!("failed to open {}: {e}", reference_path.display()))
+        .to_rgba8();
+    assert_eq!(reference.dimensions(), actual.dimensions(), "{name}: size differs from the reference");
+
+    // Differing pixels are red in the diff, the rest is a faded copy of the reference.
+    let mut differing = 0;
+    let diff = image::RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        let (a, b) = (reference.get_pixel(x, y), actual.get_pixel(x, y));
+        if a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > CHANNEL_TOLERANCE) {
+            differing += 1;
+            image::Rgba([255, 0, 0, 255])
+        } else {
+            image::Rgba(a.0.map(|v| v / 4 + 96))
+        }
+    });
+
+    let share = differing as f32 / (WIDTH * HEIGHT) as f32;
+    if share > PIXEL_TOLERANCE {
+        let out = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+        std::fs::create_dir_all(&out).unwrap();
+        let (actual_path, diff_path) = (out.join(format!("{name}.png")), out.join(format!("{name}-diff.png")));
+        actual.save(&actual_path).unwrap();
+        diff.save(&diff_path).unwrap();
+        panic!(
+            "{name}: {:.2}% of pixels differ from the reference, see {} and {}",
+            share * 100.0,
+            actual_path.display(),
+            diff_path.display()
+        );
+    }
+}
+
+fn check(name: &str, world: &World, camera: &renderer::camera::Camera) {
+    compare(name, &render(world, camera));
+}
+
+// The column the demo builds with its number keys, on a checkerboard floor.
+#[test]
+fn checkerboard_column() {
+    let floor = (-3..5).flat_map(|x| (-3..5).map(move |z| ([x, 0, z], 1 + ((x + z) & 1) as usize)));
+    let column = (1..7).flat_map(|y| {
+        [[1, 1], [1, 2], [2, 1], [2, 2]].map(|[x, z]| ([x, y, z], 1 + ((x + y + z) & 1) as usize))
+    });
+    let world = World::new(models(), floor.chain(column));
+
+    check("checkerboard_column", &world, &camera([7.0, 6.0, 7.5], [1.5, 3.0, 1.5]));
+}
+
+// A slab of blocks with a texture per face direction, seen from both sides so all six show.
+#[test]
+fn face_directions() {
+    let world = World::new(models(), (0..3).flat_map(|x| (0..3).map(move |z| ([x * 2, 0, z * 2], 3))));
+
+    check("face_directions_above", &world, &camera([7.0, 4.0, 8.0], [2.5, 0.5, 2.5]));
+    check("face_directions_below", &world, &camera([-2.0, -3.0, -2.5], [2.5, 0.5, 2.5]));
+}
+
+// A wall of the first 16 atlas tiles, seen head on.
+#[test]
+fn atlas_pattern() {
+    let world = World::new(models(), (0..16).map(|i| ([i % 4, 3 - i / 4, 0], TILE + i as usize)));
+
+    check("atlas_pattern", &world, &camera([2.0, 2.0, 5.5], [2.0, 2.0, 0.0]));
+}
@@ -0,0 +1,72 @@
+// The block walk behind `terrain::raycast`, in worlds of solid blocks.
+
+mod common;
+
+use common::World;
+use renderer::camera::Ray;
+use renderer::terrain::{raycast, BlockModel, RaycastHit, TextureId};
+
+fn world(blocks: impl IntoIterator<Item = [i32; 3]>) -> World {
+    let models = vec![BlockModel { faces: [None; 6] }, BlockModel { faces: [TextureId::new(1); 6] }];
+    World::new(models, blocks.into_iter().map(|coord| (coord, 1)))
+}
+
+fn cast(world: &World, origin: [f32; 3], dir: [f32; 3], max_distance: f32) -> Option<RaycastHit> {
+    use cgmath::InnerSpace;
+
+    let ray = Ray { origin: origin.into(), dir: cgmath::Vector3::from(dir).normalize() };
+    raycast(world, &ray, max_distance)
+}
+
+fn assert_hit(hit: Option<RaycastHit>, coord: [i32; 3], distance: f32, face: Option<u8>) {
+    let hit = hit.expect("the ray should hit a block");
+    assert_eq!(hit.coord.0, coord);
+    assert!((hit.distance - distance).abs() < 1e-5, "distance {} instead of {distance}", hit.distance);
+    assert_eq!(hit.face.map(|face| face.0), face);
+}
+
+// Rays parallel to an axis, starting right on block edges of the other axes.
+#[test]
+fn axis_aligned_from_edges() {
+    let world = world([[3, 1, 0], [0, -3, 0], [1, 1, 4]]);
+
+    assert_hit(cast(&world, [0.5, 1.0, 0.5], [1.0, 0.0, 0.0], 10.0), [3, 1, 0], 2.5, Some(0));
+    assert_hit(cast(&world, [0.0, 0.5, 0.0], [0.0, -1.0, 0.0], 10.0), [0, -3, 0], 2.5, Some(3));
+    assert_hit(cast(&world, [1.0, 1.0, 0.0], [0.0, 0.0, 1.0], 10.0), [1, 1, 4], 4.0, Some(4));
+}
+
+// Starting on an edge and moving away from it towards negative coordinates.
+#[test]
+fn negative_directions() {
+    let world = world([[0, 0, 0], [5, 5, -4]]);
+
+    assert_hit(cast(&world, [2.0, 0.5, 0.5], [-1.0, 0.0, 0.0], 10.0), [0, 0, 0], 1.0, Some(1));
+    assert_hit(cast(&world, [5.5, 5.5, 0.5], [0.0, 0.0, -1.0], 10.0), [5, 5, -4], 3.5, Some(5));
+    assert_hit(cast(&world, [3.25, 2.5, 0.5], [-1.0, -1.0, 0.0], 10.0), [0, 0, 0], 2.25 * 2f32.sqrt(), Some(1));
+}
+
+// A single block seen along each axis from both sides, and from inside.
+#[test]
+fn hit_faces() {
+    let world = world([[0, 0, 0]]);
+
+    for axis in 0..3 {
+        for (side, sign) in [(0, -1.0), (1, 1.0)] {
+            let mut origin = [0.5; 3];
+            origin[axis] += sign * 3.0;
+            let mut dir = [0.0; 3];
+            dir[axis] = -sign;
+            assert_hit(cast(&world, origin, dir, 10.0), [0, 0, 0], 2.5, Some(axis as u8 * 2 + side));
+        }
+    }
+    assert_hit(cast(&world, [0.25, 0.5, 0.75], [1.0, 1.0, 1.0], 10.0), [0, 0, 0], 0.0, None);
+}
+
+#[test]
+fn max_distance() {
+    let world = world([[3, 0, 0]]);
+
+    assert!(cast(&world, [0.5, 0.5, 0.5], [1.0, 0.0, 0.0], 2.0).is_none());
+    assert_hit(cast(&world, [0.5, 0.5, 0.5], [1.0, 0.0, 0.0], 2.5), [3, 0, 0], 2.5, Some(0));
+    assert!(cast(&world, [0.5, 0.0, 0.5], [0.0, 0.0, -1.0], 100.0).is_none());
+}